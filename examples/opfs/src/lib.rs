@@ -10,7 +10,7 @@ use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 pub async fn write_to_opfs() {
-    let fs_options = FsOptions::Local;
+    let fs_options = FsOptions::Local { root: None };
     let fs = fs_options.parse().unwrap();
     let mut file = fs
         .open_options(
@@ -31,7 +31,7 @@ pub async fn write_to_opfs() {
 
 #[wasm_bindgen]
 pub async fn read_from_opfs() {
-    let fs_options = FsOptions::Local;
+    let fs_options = FsOptions::Local { root: None };
     let fs = fs_options.parse().unwrap();
     let mut file = fs
         .open(&Path::from_opfs_path("foo").unwrap())
@@ -46,7 +46,7 @@ pub async fn read_from_opfs() {
 
 #[wasm_bindgen]
 pub async fn async_writer() {
-    let fs_options = FsOptions::Local;
+    let fs_options = FsOptions::Local { root: None };
     let fs = fs_options.parse().unwrap();
     let file = fs
         .open_options(
@@ -66,7 +66,7 @@ pub async fn async_writer() {
 
 #[wasm_bindgen]
 pub async fn async_reader() {
-    let fs_options = FsOptions::Local;
+    let fs_options = FsOptions::Local { root: None };
     let fs = fs_options.parse().unwrap();
     let file = fs
         .open(&Path::from_opfs_path("bar").unwrap())
@@ -91,7 +91,7 @@ pub async fn async_reader() {
 
 #[wasm_bindgen]
 pub async fn remove_all_dir() {
-    let fs_options = FsOptions::Local;
+    let fs_options = FsOptions::Local { root: None };
     let fs = fs_options.parse().unwrap();
     fs.remove(&Path::from_opfs_path("foo").unwrap())
         .await
@@ -1,6 +1,13 @@
 use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "bytes")]
+use core::ops::Range;
 use core::pin::Pin;
 
+#[cfg(feature = "bytes")]
+use futures_core::Stream;
+
+#[cfg(feature = "bytes")]
+use crate::MaybeSendStream;
 use crate::{
     buf::slice::{Buf, BufMut},
     error::Error,
@@ -93,6 +100,12 @@ pub unsafe trait DynRead: MaybeSend + MaybeSync + seal::Sealed {
     ) -> Pin<Box<dyn MaybeSendFuture<Output = (Result<(), Error>, Vec<u8>)> + '_>>;
 
     fn size(&self) -> Pin<Box<dyn MaybeSendFuture<Output = Result<u64, Error>> + '_>>;
+
+    #[cfg(feature = "bytes")]
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> Pin<Box<dyn MaybeSendStream<Item = Result<bytes::Bytes, Error>> + '_>>;
 }
 
 unsafe impl<R> DynRead for R
@@ -124,6 +137,14 @@ where
     fn size(&self) -> Pin<Box<dyn MaybeSendFuture<Output = Result<u64, Error>> + '_>> {
         Box::pin(async move { R::size(self).await })
     }
+
+    #[cfg(feature = "bytes")]
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> Pin<Box<dyn MaybeSendStream<Item = Result<bytes::Bytes, Error>> + '_>> {
+        Box::pin(R::read_stream(self, range))
+    }
 }
 
 impl Read for Box<dyn DynRead + '_> {
@@ -142,4 +163,12 @@ impl Read for Box<dyn DynRead + '_> {
     async fn size(&self) -> Result<u64, Error> {
         DynRead::size(self.as_ref()).await
     }
+
+    #[cfg(feature = "bytes")]
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend + '_ {
+        DynRead::read_stream(self.as_mut(), range)
+    }
 }
@@ -17,6 +17,8 @@ pub enum Error {
     Remote(BoxedError),
     #[error("unsupported operation: {message}")]
     Unsupported { message: String },
+    #[error("precondition failed: {message}")]
+    PreconditionFailed { message: String },
     #[error("Performs dynamic cast failed.")]
     CastError,
     #[error("Error occurs in wasm: {message}")]
@@ -24,3 +26,127 @@ pub enum Error {
     #[error(transparent)]
     Other(BoxedError),
 }
+
+/// A portable classification of [`Error`] that does not depend on the backend that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The file or object does not exist.
+    NotFound,
+    /// The file or object already exists.
+    AlreadyExists,
+    /// The credentials are missing, invalid or not allowed to perform the operation.
+    PermissionDenied,
+    /// A conditional operation failed, e.g. the file was modified concurrently.
+    PreconditionFailed,
+    /// The backend rejected the request because of rate limiting. Retrying later may succeed.
+    Throttled,
+    /// The operation did not complete in time.
+    Timeout,
+    /// The backend failed transiently, e.g. with an internal server error or a reset connection.
+    Unavailable,
+    /// The operation is not supported by the backend.
+    Unsupported,
+    /// The data read or received is malformed.
+    Corrupted,
+    /// Any other error.
+    Other,
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::ErrorKind> for ErrorKind {
+    fn from(kind: std::io::ErrorKind) -> Self {
+        use std::io::ErrorKind as IoKind;
+
+        match kind {
+            IoKind::NotFound => ErrorKind::NotFound,
+            IoKind::AlreadyExists => ErrorKind::AlreadyExists,
+            IoKind::PermissionDenied => ErrorKind::PermissionDenied,
+            IoKind::TimedOut => ErrorKind::Timeout,
+            IoKind::ConnectionRefused
+            | IoKind::ConnectionReset
+            | IoKind::ConnectionAborted
+            | IoKind::NotConnected
+            | IoKind::BrokenPipe
+            | IoKind::Interrupted => ErrorKind::Unavailable,
+            IoKind::Unsupported => ErrorKind::Unsupported,
+            IoKind::InvalidData | IoKind::UnexpectedEof => ErrorKind::Corrupted,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl ErrorKind {
+    /// Returns `true` if the same operation may succeed when it is retried later.
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Throttled | ErrorKind::Timeout | ErrorKind::Unavailable
+        )
+    }
+}
+
+/// A backend error tagged with the [`ErrorKind`] it maps to, created by [`Error::with_kind`].
+#[derive(Debug, Error)]
+#[error("{source}")]
+pub struct KindError {
+    kind: ErrorKind,
+    source: BoxedError,
+}
+
+impl KindError {
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl Error {
+    /// Wraps an error of a remote backend as [`Error::Remote`], tagged with the [`ErrorKind`]
+    /// that [`Error::kind`] reports for it.
+    pub fn with_kind(kind: ErrorKind, source: impl Into<BoxedError>) -> Self {
+        Error::Remote(Box::new(KindError {
+            kind,
+            source: source.into(),
+        }))
+    }
+
+    /// Returns the kind of this error.
+    ///
+    /// Boxed errors are inspected for a [`KindError`], a nested [`Error`] or a
+    /// [`std::io::Error`]; anything else is [`ErrorKind::Other`].
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => io_error_kind(err),
+            Error::Path(err) | Error::Remote(err) | Error::Other(err) => {
+                boxed_error_kind(err.as_ref())
+            }
+            Error::Unsupported { .. } => ErrorKind::Unsupported,
+            Error::PreconditionFailed { .. } => ErrorKind::PreconditionFailed,
+            Error::CastError | Error::Wasm { .. } => ErrorKind::Other,
+        }
+    }
+}
+
+fn boxed_error_kind(err: &(dyn core::error::Error + 'static)) -> ErrorKind {
+    if let Some(err) = err.downcast_ref::<KindError>() {
+        return err.kind;
+    }
+    if let Some(err) = err.downcast_ref::<Error>() {
+        return err.kind();
+    }
+    #[cfg(feature = "std")]
+    if let Some(err) = err.downcast_ref::<std::io::Error>() {
+        return io_error_kind(err);
+    }
+
+    ErrorKind::Other
+}
+
+#[cfg(feature = "std")]
+fn io_error_kind(err: &std::io::Error) -> ErrorKind {
+    match (err.kind().into(), err.get_ref()) {
+        (ErrorKind::Other, Some(inner)) => boxed_error_kind(inner),
+        (kind, _) => kind,
+    }
+}
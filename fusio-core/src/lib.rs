@@ -12,13 +12,21 @@ pub mod error;
 mod maybe;
 
 use core::future::Future;
+#[cfg(all(feature = "alloc", feature = "bytes"))]
+use core::ops::Range;
 
 pub use buf::{IoBuf, IoBufMut};
 #[cfg(feature = "alloc")]
 #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
 pub use dynamic::{DynRead, DynWrite};
 use error::Error;
-pub use maybe::{MaybeOwned, MaybeSend, MaybeSendFuture, MaybeSync};
+#[cfg(all(feature = "alloc", feature = "bytes"))]
+use futures_core::Stream;
+pub use maybe::{MaybeOwned, MaybeSend, MaybeSendFuture, MaybeSendStream, MaybeSync};
+
+/// The size of the chunks [`Read::read_stream`] reads at a time, unless it is implemented natively.
+#[cfg(all(feature = "alloc", feature = "bytes"))]
+const READ_STREAM_CHUNK_SIZE: u64 = 64 * 1024;
 
 /// The core trait for writing data.
 ///
@@ -119,6 +127,37 @@ pub trait Read: MaybeSend + MaybeSync {
     ) -> impl Future<Output = (Result<(), Error>, alloc::vec::Vec<u8>)> + MaybeSend;
 
     fn size(&self) -> impl Future<Output = Result<u64, Error>> + MaybeSend;
+
+    /// Reads the bytes in `range` as a stream of chunks, so that large files can be passed on,
+    /// e.g. to a response or a decompressor, in bounded memory. The stream ends with an error if
+    /// the file ends before `range.end`.
+    ///
+    /// By default the chunks are read one after another with [`Read::read_exact_at`], remote
+    /// files hand out the body of a single request as it arrives instead.
+    #[cfg(all(feature = "alloc", feature = "bytes"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "alloc", feature = "bytes"))))]
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend + '_ {
+        futures_util::stream::unfold(Some((self, range)), |state| async move {
+            let (reader, range) = state?;
+            if range.is_empty() {
+                return None;
+            }
+            let len = (range.end - range.start).min(READ_STREAM_CHUNK_SIZE);
+            let (result, buf) = reader
+                .read_exact_at(alloc::vec![0; len as usize], range.start)
+                .await;
+            match result {
+                Ok(()) => Some((
+                    Ok(bytes::Bytes::from(buf)),
+                    Some((reader, range.start + len..range.end)),
+                )),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
 }
 
 impl<R: Read> Read for &mut R {
@@ -142,6 +181,14 @@ impl<R: Read> Read for &mut R {
     fn size(&self) -> impl Future<Output = Result<u64, Error>> + MaybeSend {
         R::size(self)
     }
+
+    #[cfg(all(feature = "alloc", feature = "bytes"))]
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend + '_ {
+        R::read_stream(self, range)
+    }
 }
 
 impl<W: Write> Write for &mut W {
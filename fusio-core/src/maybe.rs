@@ -1,5 +1,7 @@
 use core::future::Future;
 
+use futures_core::Stream;
+
 /// A trait representing types that may or may not require [`Send`].
 ///
 /// Many async runtimes do not require [`Send`] for futures and streams. This trait
@@ -96,3 +98,10 @@ unsafe impl<T: 'static> MaybeOwned for T {}
 pub trait MaybeSendFuture: Future + MaybeSend {}
 
 impl<F> MaybeSendFuture for F where F: Future + MaybeSend {}
+
+/// A trait representing streams that may or may not require [`Send`].
+///
+/// Like [`MaybeSendFuture`], but for [`Stream`].
+pub trait MaybeSendStream: Stream + MaybeSend {}
+
+impl<S> MaybeSendStream for S where S: Stream + MaybeSend {}
@@ -1,20 +1,32 @@
 use std::sync::Arc;
 
-use fusio::{error::Error, DynFs};
+use fusio::{error::Error, fs::Fs, path::Path, prefix::PrefixFs, DynFs};
 
+/// The options of a file system to create with [`FsOptions::parse`].
+///
+/// Every variant takes an optional `root`, which scopes the file system under that path with a
+/// [`PrefixFs`].
 #[derive(Clone)]
 #[non_exhaustive]
 pub enum FsOptions {
     #[cfg(any(feature = "tokio", feature = "monoio", feature = "opfs"))]
-    Local,
+    Local { root: Option<Path> },
+    /// In-memory file system. Clones of the same [`MemoryFs`](fusio::memory::MemoryFs) share
+    /// their files, so the same options can be parsed repeatedly without losing data.
+    Memory {
+        fs: fusio::memory::MemoryFs,
+        root: Option<Path>,
+    },
     #[cfg(feature = "aws")]
     S3 {
+        root: Option<Path>,
         bucket: String,
         credential: Option<fusio::remotes::aws::AwsCredential>,
         endpoint: Option<String>,
         region: Option<String>,
         sign_payload: Option<bool>,
         checksum: Option<bool>,
+        encryption: Option<fusio::remotes::aws::ServerSideEncryption>,
     },
 }
 
@@ -22,18 +34,22 @@ impl FsOptions {
     pub fn parse(self) -> Result<Arc<dyn DynFs>, Error> {
         match self {
             #[cfg(any(feature = "tokio", feature = "monoio", feature = "opfs"))]
-            FsOptions::Local => Ok(Arc::new(fusio::disk::LocalFs {})),
+            FsOptions::Local { root } => Ok(scoped(fusio::disk::LocalFs {}, root)),
+            FsOptions::Memory { fs, root } => Ok(scoped(fs, root)),
             #[cfg(feature = "object_store")]
             FsOptions::S3 {
+                root,
                 bucket,
                 credential,
                 endpoint,
                 region,
                 sign_payload,
                 checksum,
+                encryption,
             } => {
+                use fusio::remotes::aws::ServerSideEncryption;
                 use fusio_object_store::fs::S3Store;
-                use object_store::aws::AmazonS3Builder;
+                use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
 
                 let mut builder = AmazonS3Builder::new().with_bucket_name(bucket);
 
@@ -58,18 +74,49 @@ impl FsOptions {
                 if matches!(checksum, Some(true)) {
                     builder = builder.with_checksum_algorithm(object_store::aws::Checksum::SHA256);
                 }
-                Ok(Arc::new(S3Store::from(
-                    builder.build().map_err(|e| fusio::Error::Other(e.into()))?,
-                )) as Arc<dyn DynFs>)
+                let sse_type = |builder: AmazonS3Builder, sse_type: &str| {
+                    let key = "aws_server_side_encryption"
+                        .parse::<AmazonS3ConfigKey>()
+                        .expect("object_store supports server-side encryption");
+                    builder.with_config(key, sse_type)
+                };
+                match encryption {
+                    Some(ServerSideEncryption::S3) => builder = sse_type(builder, "AES256"),
+                    Some(ServerSideEncryption::Kms { key_id, context }) => {
+                        if !context.is_empty() {
+                            return Err(Error::Unsupported {
+                                message: "object_store does not support SSE-KMS encryption \
+                                          contexts"
+                                    .to_string(),
+                            });
+                        }
+                        builder = match key_id {
+                            Some(key_id) => builder.with_sse_kms_encryption(key_id),
+                            None => sse_type(builder, "aws:kms"),
+                        };
+                    }
+                    Some(ServerSideEncryption::Customer { key }) => {
+                        use base64::{prelude::BASE64_STANDARD, Engine};
+
+                        builder = builder.with_ssec_encryption(BASE64_STANDARD.encode(key));
+                    }
+                    None => {}
+                }
+                Ok(scoped(
+                    S3Store::from(builder.build().map_err(|e| fusio::Error::Other(e.into()))?),
+                    root,
+                ))
             }
             #[cfg(all(feature = "aws", not(feature = "object_store")))]
             FsOptions::S3 {
+                root,
                 bucket,
                 credential,
                 endpoint,
                 region,
                 sign_payload,
                 checksum,
+                encryption,
             } => {
                 use fusio::remotes::aws::fs::AmazonS3Builder;
 
@@ -90,8 +137,19 @@ impl FsOptions {
                 if let Some(checksum) = checksum {
                     builder = builder.checksum(checksum);
                 }
-                Ok(Arc::new(builder.build()))
+                if let Some(encryption) = encryption {
+                    builder = builder.encryption(encryption);
+                }
+                Ok(scoped(builder.build(), root))
             }
         }
     }
 }
+
+/// Scopes `fs` under `root`, if there is one.
+fn scoped<F: Fs + 'static>(fs: F, root: Option<Path>) -> Arc<dyn DynFs> {
+    match root {
+        Some(root) => PrefixFs::new(fs, root).into_dyn(),
+        None => Arc::new(fs),
+    }
+}
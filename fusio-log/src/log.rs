@@ -384,6 +384,7 @@ mod tests {
         let token = std::option_env!("AWS_SESSION_TOKEN").map(|v| v.to_string());
 
         let option = Options::new(path).truncate(true).fs(FsOptions::S3 {
+            root: None,
             bucket,
             credential: Some(AwsCredential {
                 key_id,
@@ -394,6 +395,7 @@ mod tests {
             region: Some(region),
             sign_payload: None,
             checksum: None,
+            encryption: None,
         });
 
         {
@@ -37,7 +37,7 @@ impl Options {
         Self {
             path,
             buf_size: DEFAULT_BUF_SIZE,
-            fs_option: FsOptions::Local,
+            fs_option: FsOptions::Local { root: None },
             truncate: false,
         }
     }
@@ -3,14 +3,14 @@ use std::sync::Arc;
 use async_stream::stream;
 use fusio::{
     error::Error,
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
-    path::Path,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::{Path, DELIMITER},
 };
 use futures_core::Stream;
-use futures_util::stream::StreamExt;
-use object_store::ObjectStore;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use object_store::{ObjectMeta, ObjectStore, PutMode, UpdateVersion};
 
-use crate::{BoxedError, S3File};
+use crate::{object_store_error, S3File};
 
 pub struct S3Store<O: ObjectStore> {
     inner: Arc<O>,
@@ -24,6 +24,17 @@ impl<O: ObjectStore> From<O> for S3Store<O> {
     }
 }
 
+fn object_meta(meta: ObjectMeta) -> FileMeta {
+    FileMeta {
+        path: meta.location.into(),
+        size: meta.size as u64,
+        last_modified: Some(meta.last_modified.into()),
+        e_tag: meta.e_tag,
+        version: meta.version,
+        is_dir: false,
+    }
+}
+
 impl<O: ObjectStore> Fs for S3Store<O> {
     type File = S3File<O>;
 
@@ -32,15 +43,27 @@ impl<O: ObjectStore> Fs for S3Store<O> {
     }
 
     async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
-        if !options.truncate {
+        // an object that must not exist yet has nothing to truncate
+        if !options.truncate && !options.create_new {
             return Err(Error::Unsupported {
                 message: "append mode is not supported in Amazon S3".into(),
             });
         }
+        let mode = if options.create_new {
+            Some(PutMode::Create)
+        } else {
+            options.if_match.map(|e_tag| {
+                PutMode::Update(UpdateVersion {
+                    e_tag: Some(e_tag),
+                    version: None,
+                })
+            })
+        };
         Ok(S3File {
             inner: self.inner.clone(),
             path: path.clone().into(),
             buf: None,
+            conditional: mode.map(|mode| (mode, Vec::new())),
         })
     }
 
@@ -56,18 +79,128 @@ impl<O: ObjectStore> Fs for S3Store<O> {
         let mut stream = self.inner.list(Some(&path));
 
         Ok(stream! {
-            while let Some(meta) = stream.next().await.transpose().map_err(|err| Error::Remote(BoxedError::from(err)))? {
-                yield Ok(FileMeta { path: meta.location.into(), size: meta.size as u64 });
+            while let Some(meta) = stream.next().await.transpose().map_err(object_store_error)? {
+                yield Ok(object_meta(meta));
             }
         })
     }
 
+    /// Lists with [`ObjectStore::list_with_delimiter`], or with [`ObjectStore::list_with_offset`]
+    /// if the listing is recursive and starts after a path.
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        let prefix = path.clone().into();
+        let limit = options.limit.unwrap_or(usize::MAX);
+        if options.recursive {
+            let objects = match options.start_after {
+                Some(start_after) => self
+                    .inner
+                    .list_with_offset(Some(&prefix), &start_after.into()),
+                None => self.inner.list(Some(&prefix)),
+            };
+
+            return Ok(objects
+                .map_ok(object_meta)
+                .map_err(object_store_error)
+                .take(limit)
+                .boxed());
+        }
+        if options.delimiter != DELIMITER {
+            return Err(Error::Unsupported {
+                message: format!("listing with the delimiter {:?}", options.delimiter),
+            });
+        }
+
+        let result = self
+            .inner
+            .list_with_delimiter(Some(&prefix))
+            .await
+            .map_err(object_store_error)?;
+        // directories sort by their prefix, which ends with the delimiter
+        let mut entries = result
+            .objects
+            .into_iter()
+            .map(|meta| (meta.location.to_string(), object_meta(meta)))
+            .chain(result.common_prefixes.into_iter().map(|prefix| {
+                (
+                    format!("{prefix}{DELIMITER}"),
+                    FileMeta {
+                        path: prefix.into(),
+                        is_dir: true,
+                        ..Default::default()
+                    },
+                )
+            }))
+            .filter(|(key, meta)| match &options.start_after {
+                Some(start_after) => {
+                    let start_after = start_after.as_ref();
+                    key.as_str() > start_after || (meta.is_dir && start_after.starts_with(key))
+                }
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(stream::iter(entries.into_iter().map(|(_, meta)| Ok(meta)))
+            .take(limit)
+            .boxed())
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let path = path.clone().into();
+        let meta = self.inner.head(&path).await.map_err(object_store_error)?;
+
+        Ok(object_meta(meta))
+    }
+
     async fn remove(&self, path: &Path) -> Result<(), Error> {
         let path = path.clone().into();
-        self.inner
-            .delete(&path)
-            .await
-            .map_err(|err| Error::Remote(BoxedError::from(err)))?;
+        self.inner.delete(&path).await.map_err(object_store_error)?;
+
+        Ok(())
+    }
+
+    /// Removes the objects with [`ObjectStore::delete_stream`], which deletes many objects per
+    /// request on stores that support it.
+    async fn remove_batch(&self, paths: &[Path]) -> Result<Vec<Result<(), Error>>, Error> {
+        let locations = paths
+            .iter()
+            .map(|path| Ok(path.clone().into()))
+            .collect::<Vec<_>>();
+        let results = self
+            .inner
+            .delete_stream(stream::iter(locations).boxed())
+            .collect::<Vec<_>>()
+            .await;
+        // a failed request is reported once in place of the results of all the paths it held
+        if results.len() != paths.len() {
+            return Err(results.into_iter().find_map(Result::err).map_or_else(
+                || Error::Other("incomplete results of delete_stream".into()),
+                object_store_error,
+            ));
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.map(|_| ()).map_err(object_store_error))
+            .collect())
+    }
+
+    /// Removes the objects beneath `path` with [`ObjectStore::delete_stream`] as they are listed.
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        let prefix = path.clone().into();
+        let locations = self
+            .inner
+            .list(Some(&prefix))
+            .map_ok(|meta| meta.location)
+            .boxed();
+        let mut deleted = self.inner.delete_stream(locations);
+        while let Some(result) = deleted.next().await {
+            result.map_err(object_store_error)?;
+        }
 
         Ok(())
     }
@@ -79,7 +212,7 @@ impl<O: ObjectStore> Fs for S3Store<O> {
         self.inner
             .copy(&from, &to)
             .await
-            .map_err(|err| Error::Remote(BoxedError::from(err)))?;
+            .map_err(object_store_error)?;
 
         Ok(())
     }
@@ -89,4 +222,204 @@ impl<O: ObjectStore> Fs for S3Store<O> {
             message: "s3 does not support link file".to_string(),
         })
     }
+
+    /// Renames an object with [`ObjectStore::rename`], which is a copy followed by a delete on
+    /// object stores and therefore not atomic.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let from = from.clone().into();
+        let to = to.clone().into();
+
+        self.inner
+            .rename(&from, &to)
+            .await
+            .map_err(object_store_error)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fusio::{
+        error::{Error, ErrorKind},
+        fs::{Fs, ListOptions, OpenOptions},
+        path::Path,
+        Write,
+    };
+    use futures_util::TryStreamExt;
+    use object_store::memory::InMemory;
+
+    use crate::fs::S3Store;
+
+    #[tokio::test]
+    async fn test_conditional_write() {
+        let fs = S3Store::from(InMemory::new());
+        let path = Path::parse("manifest").unwrap();
+
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create_new(true))
+            .await
+            .unwrap();
+        file.write_all(&b"v1"[..]).await.0.unwrap();
+        file.close().await.unwrap();
+
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create_new(true))
+            .await
+            .unwrap();
+        file.write_all(&b"v0"[..]).await.0.unwrap();
+        assert!(matches!(
+            file.close().await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+
+        let e_tag = fs.metadata(&path).await.unwrap().e_tag.unwrap();
+        let mut file = fs
+            .open_options(
+                &path,
+                OpenOptions::default()
+                    .truncate(true)
+                    .if_match(e_tag.clone()),
+            )
+            .await
+            .unwrap();
+        file.write_all(&b"v2"[..]).await.0.unwrap();
+        file.close().await.unwrap();
+
+        let mut file = fs
+            .open_options(
+                &path,
+                OpenOptions::default()
+                    .truncate(true)
+                    .if_match(e_tag.clone()),
+            )
+            .await
+            .unwrap();
+        file.write_all(&b"v3"[..]).await.0.unwrap();
+        assert!(matches!(
+            file.close().await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+
+        let meta = fs.metadata(&path).await.unwrap();
+        assert_eq!(meta.size, 2);
+        assert_eq!(
+            fs.metadata(&Path::parse("missing").unwrap())
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+        assert_ne!(meta.e_tag, Some(e_tag));
+    }
+
+    #[tokio::test]
+    async fn test_list_with() {
+        let fs = S3Store::from(InMemory::new());
+        for name in ["logs/a", "logs/b-x", "logs/b/c", "logs/c"] {
+            let mut file = fs
+                .open_options(
+                    &Path::parse(name).unwrap(),
+                    OpenOptions::default().truncate(true),
+                )
+                .await
+                .unwrap();
+            file.write_all(&b"data"[..]).await.0.unwrap();
+            file.close().await.unwrap();
+        }
+        let list = |options: ListOptions| {
+            let fs = &fs;
+            async move {
+                fs.list_with(&Path::parse("logs").unwrap(), options)
+                    .await?
+                    .map_ok(|meta| (meta.path.to_string(), meta.is_dir))
+                    .try_collect::<Vec<_>>()
+                    .await
+            }
+        };
+        let entry = |name: &str, is_dir: bool| (name.to_string(), is_dir);
+
+        assert_eq!(
+            list(ListOptions::default()).await.unwrap(),
+            [
+                entry("logs/a", false),
+                entry("logs/b-x", false),
+                entry("logs/b", true),
+                entry("logs/c", false),
+            ]
+        );
+        assert_eq!(
+            list(
+                ListOptions::default()
+                    .start_after(Path::parse("logs/b-x").unwrap())
+                    .limit(1)
+            )
+            .await
+            .unwrap(),
+            [entry("logs/b", true)]
+        );
+        assert_eq!(
+            list(
+                ListOptions::default()
+                    .recursive(true)
+                    .start_after(Path::parse("logs/a").unwrap())
+                    .limit(2)
+            )
+            .await
+            .unwrap(),
+            [entry("logs/b-x", false), entry("logs/b/c", false)]
+        );
+        assert_eq!(
+            list(ListOptions::default().delimiter("-"))
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Unsupported
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_batch() {
+        let fs = S3Store::from(InMemory::new());
+        for name in ["table/a", "table/b/c", "table-old/d"] {
+            let mut file = fs
+                .open_options(
+                    &Path::parse(name).unwrap(),
+                    OpenOptions::default().truncate(true),
+                )
+                .await
+                .unwrap();
+            file.write_all(&b"data"[..]).await.0.unwrap();
+            file.close().await.unwrap();
+        }
+
+        let results = fs
+            .remove_batch(&[
+                Path::parse("table/a").unwrap(),
+                Path::parse("missing").unwrap(),
+            ])
+            .await
+            .unwrap();
+        // like S3, the in-memory store counts a missing object as deleted
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            fs.metadata(&Path::parse("table/a").unwrap())
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+
+        fs.remove_dir_all(&Path::parse("table").unwrap())
+            .await
+            .unwrap();
+        assert!(fs
+            .metadata(&Path::parse("table/b/c").unwrap())
+            .await
+            .is_err());
+        assert!(fs
+            .metadata(&Path::parse("table-old/d").unwrap())
+            .await
+            .is_ok());
+    }
 }
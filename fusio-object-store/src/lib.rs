@@ -1,10 +1,15 @@
 pub mod fs;
 
-use std::{ops::Range, sync::Arc};
+use std::{io, ops::Range, sync::Arc};
 
-use fusio::{error::Error, IoBuf, IoBufMut, Read, Write};
-use futures_util::lock::Mutex;
-use object_store::{buffered::BufWriter, path::Path, GetOptions, GetRange, ObjectStore};
+use bytes::Bytes;
+use fusio::{
+    error::{Error, ErrorKind},
+    IoBuf, IoBufMut, MaybeSend, Read, Write,
+};
+use futures_core::Stream;
+use futures_util::{lock::Mutex, stream, StreamExt, TryStreamExt};
+use object_store::{buffered::BufWriter, path::Path, GetOptions, GetRange, ObjectStore, PutMode};
 use parquet::arrow::async_writer::{AsyncFileWriter, ParquetObjectWriter};
 
 pub type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
@@ -13,6 +18,40 @@ pub struct S3File<O: ObjectStore> {
     inner: Arc<O>,
     path: Path,
     buf: Option<Arc<Mutex<ParquetObjectWriter>>>,
+    /// The content of a conditional write, published with a single `put_opts` on close since
+    /// multipart uploads can not be conditional.
+    conditional: Option<(PutMode, Vec<u8>)>,
+}
+
+/// Converts an `object_store::Error` into a `fusio::Error` tagged with its [`ErrorKind`].
+pub(crate) fn object_store_error(err: object_store::Error) -> Error {
+    let kind = match &err {
+        object_store::Error::NotFound { .. } => ErrorKind::NotFound,
+        object_store::Error::AlreadyExists { .. } => ErrorKind::AlreadyExists,
+        object_store::Error::Precondition { .. } | object_store::Error::NotModified { .. } => {
+            ErrorKind::PreconditionFailed
+        }
+        object_store::Error::PermissionDenied { .. }
+        | object_store::Error::Unauthenticated { .. } => ErrorKind::PermissionDenied,
+        object_store::Error::NotSupported { .. } | object_store::Error::NotImplemented => {
+            ErrorKind::Unsupported
+        }
+        _ => ErrorKind::Other,
+    };
+    Error::with_kind(kind, err)
+}
+
+/// Converts the error of a conditional put, where an existing object also means the
+/// precondition failed.
+pub(crate) fn put_error(err: object_store::Error) -> Error {
+    match err {
+        object_store::Error::AlreadyExists { .. } | object_store::Error::Precondition { .. } => {
+            Error::PreconditionFailed {
+                message: err.to_string(),
+            }
+        }
+        err => object_store_error(err),
+    }
 }
 
 impl<O: ObjectStore> S3File<O> {
@@ -29,15 +68,15 @@ impl<O: ObjectStore> S3File<O> {
             .inner
             .get_opts(&self.path, opts)
             .await
-            .map_err(BoxedError::from)
+            .map_err(object_store_error)
         {
             Ok(result) => result,
-            Err(e) => return (Err(Error::Remote(e.into())), buf),
+            Err(e) => return (Err(e), buf),
         };
 
-        let bytes = match result.bytes().await.map_err(BoxedError::from) {
+        let bytes = match result.bytes().await.map_err(object_store_error) {
             Ok(bytes) => bytes,
-            Err(e) => return (Err(Error::Other(e.into())), buf),
+            Err(e) => return (Err(e), buf),
         };
 
         buf.as_slice_mut().copy_from_slice(&bytes);
@@ -74,13 +113,49 @@ impl<O: ObjectStore> Read for S3File<O> {
             .inner
             .get_opts(&self.path, options)
             .await
-            .map_err(|err| Error::Remote(err.into()))?;
+            .map_err(object_store_error)?;
         Ok(response.meta.size as u64)
     }
+
+    /// Streams the body of a single ranged `get_opts`.
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<Bytes, Error>> + MaybeSend + '_ {
+        stream::once(async move {
+            if range.is_empty() {
+                return Ok(stream::empty().right_stream());
+            }
+            let options = GetOptions {
+                range: Some(GetRange::Bounded(range.clone())),
+                ..Default::default()
+            };
+            let result = self
+                .inner
+                .get_opts(&self.path, options)
+                .await
+                .map_err(object_store_error)?;
+            if result.range != range {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("object ends before the end of the range {range:?}"),
+                )));
+            }
+            Ok(result
+                .into_stream()
+                .map_err(object_store_error)
+                .left_stream())
+        })
+        .try_flatten()
+    }
 }
 
 impl<O: ObjectStore> Write for S3File<O> {
     async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
+        if let Some((_, content)) = self.conditional.as_mut() {
+            content.extend_from_slice(buf.as_slice());
+            return (Ok(()), buf);
+        }
         let buf_writer = match self.buf {
             Some(ref mut buf) => buf,
             None => {
@@ -102,6 +177,12 @@ impl<O: ObjectStore> Write for S3File<O> {
     }
 
     async fn close(&mut self) -> Result<(), Error> {
+        if let Some((mode, content)) = self.conditional.take() {
+            self.inner
+                .put_opts(&self.path, content.into(), mode.into())
+                .await
+                .map_err(put_error)?;
+        }
         if let Some(buf) = self.buf.take() {
             buf.lock()
                 .await
@@ -153,6 +234,7 @@ mod tests {
                 inner: Arc::new(s3),
                 path,
                 buf: None,
+                conditional: None,
             };
             let (result, bytes) = store.write_all(Bytes::from("hello! Fusio!")).await;
             result.unwrap();
@@ -163,4 +245,43 @@ mod tests {
             assert_eq!(buf, &bytes[..]);
         }
     }
+
+    #[tokio::test]
+    async fn read_stream() {
+        use std::{io, sync::Arc};
+
+        use fusio::error::Error;
+        use futures_util::TryStreamExt;
+        use object_store::{memory::InMemory, path::Path, ObjectStore};
+
+        use crate::{Read, S3File};
+
+        let store = InMemory::new();
+        let path = Path::from("object");
+        store.put(&path, "hello! Fusio!".into()).await.unwrap();
+        let mut file = S3File {
+            inner: Arc::new(store),
+            path,
+            buf: None,
+            conditional: None,
+        };
+
+        let chunks = file
+            .read_stream(7..12)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"Fusio");
+        let chunks = file
+            .read_stream(3..3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(chunks.is_empty());
+        let result = file.read_stream(7..20).try_collect::<Vec<_>>().await;
+        assert!(matches!(
+            result,
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
 }
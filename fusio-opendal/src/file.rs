@@ -1,4 +1,9 @@
-use fusio::{error::Error, fs::OpenOptions, IoBuf, IoBufMut, Read, Write};
+use std::{io, ops::Range};
+
+use bytes::Bytes;
+use fusio::{error::Error, fs::OpenOptions, IoBuf, IoBufMut, MaybeSend, Read, Write};
+use futures_core::Stream;
+use futures_util::{stream, StreamExt, TryStreamExt};
 use opendal::{Operator, Reader, Writer};
 
 use crate::utils::parse_opendal_error;
@@ -14,6 +19,11 @@ pub struct OpendalFile {
 enum FileState {
     Read(Reader),
     Write(Writer),
+    /// Content buffered until close, where it is written only if the file does not exist yet.
+    ///
+    /// opendal's streaming writer can not carry conditions, so create-only writes are published
+    /// with a single `write_with(..).if_none_match("*")`.
+    CreateNew(Option<Vec<u8>>),
 }
 
 impl OpendalFile {
@@ -25,6 +35,12 @@ impl OpendalFile {
         path: String,
         options: OpenOptions,
     ) -> Result<Self, Error> {
+        if options.if_match.is_some() {
+            return Err(Error::Unsupported {
+                message: "opendal does not support conditional writes with if_match".to_string(),
+            });
+        }
+
         // open as read
         if options.read && !options.write && !options.create && !options.truncate {
             let r = op
@@ -39,6 +55,15 @@ impl OpendalFile {
             });
         }
 
+        // open as create-only write, the service rejects the content on close if the file exists
+        if !options.read && options.write && options.create_new {
+            return Ok(Self {
+                op,
+                path,
+                state: FileState::CreateNew(Some(Vec::new())),
+            });
+        }
+
         // open as truncate write
         //
         // TODO: we only support `create && truncate` for now, maybe we can check if the file exists
@@ -117,11 +142,60 @@ impl Read for OpendalFile {
             .map_err(parse_opendal_error)?;
         Ok(meta.content_length())
     }
+
+    /// Streams the chunks opendal reads for `range`.
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<Bytes, Error>> + MaybeSend + '_ {
+        let reader = match &self.state {
+            FileState::Read(r) => Ok(r.clone()),
+            _ => Err(Error::Other("file is not open as read mode".into())),
+        };
+        stream::once(async move {
+            let len = range.end.saturating_sub(range.start);
+            let chunks = reader?
+                .into_bytes_stream(range)
+                .await
+                .map_err(parse_opendal_error)?;
+            Ok::<_, Error>(exactly(chunks.map_err(Error::Io), len))
+        })
+        .try_flatten()
+    }
+}
+
+/// Ends `chunks` with an error if they add up to fewer than `len` bytes.
+fn exactly<S>(chunks: S, len: u64) -> impl Stream<Item = Result<Bytes, Error>>
+where
+    S: Stream<Item = Result<Bytes, Error>> + Unpin,
+{
+    stream::unfold(Some((chunks, len)), |state| async move {
+        let (mut chunks, remaining) = state?;
+        match chunks.next().await {
+            Some(Ok(chunk)) => {
+                let remaining = remaining.saturating_sub(chunk.len() as u64);
+                Some((Ok(chunk), Some((chunks, remaining))))
+            }
+            Some(Err(err)) => Some((Err(err), None)),
+            None if remaining > 0 => Some((
+                Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("file ends {remaining} bytes before the end of the range"),
+                ))),
+                None,
+            )),
+            None => None,
+        }
+    })
 }
 
 impl Write for OpendalFile {
     /// TODO: opendal has native buffer support, maybe we can tune it while open writer.
     async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
+        if let FileState::CreateNew(Some(content)) = &mut self.state {
+            content.extend_from_slice(buf.as_slice());
+            return (Ok(()), buf);
+        }
         let FileState::Write(w) = &mut self.state else {
             return (
                 Err(Error::Other("file is not open as write mode".into())),
@@ -143,6 +217,17 @@ impl Write for OpendalFile {
     }
 
     async fn close(&mut self) -> Result<(), Error> {
+        if let FileState::CreateNew(content) = &mut self.state {
+            let Some(content) = content.take() else {
+                return Ok(());
+            };
+            return self
+                .op
+                .write_with(&self.path, content)
+                .if_none_match("*")
+                .await
+                .map_err(parse_opendal_error);
+        }
         let FileState::Write(w) = &mut self.state else {
             return Err(Error::Other("file is not open as write mode".into()));
         };
@@ -1,11 +1,13 @@
+use std::future::ready;
+
 use fusio::{
     error::Error,
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
-    path::Path,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::{Path, DELIMITER},
 };
 use futures_core::Stream;
-use futures_util::TryStreamExt;
-use opendal::{Metakey, Operator};
+use futures_util::{StreamExt, TryStreamExt};
+use opendal::{Metadata, Metakey, Operator, Scheme};
 
 use crate::{utils::parse_opendal_error, OpendalFile};
 
@@ -22,6 +24,17 @@ impl From<Operator> for OpendalFs {
     }
 }
 
+fn file_meta(path: &str, metadata: &Metadata) -> FileMeta {
+    FileMeta {
+        path: path.into(),
+        size: metadata.content_length(),
+        last_modified: metadata.last_modified().map(Into::into),
+        e_tag: metadata.etag().map(str::to_string),
+        version: metadata.version().map(str::to_string),
+        is_dir: metadata.is_dir(),
+    }
+}
+
 impl Fs for OpendalFs {
     type File = OpendalFile;
 
@@ -45,16 +58,72 @@ impl Fs for OpendalFs {
         Ok(self
             .op
             .lister_with(path.as_ref())
-            .metakey(Metakey::ContentLength)
+            .metakey(
+                Metakey::ContentLength
+                    | Metakey::LastModified
+                    | Metakey::Etag
+                    | Metakey::Version
+                    | Metakey::Mode,
+            )
             .await
             .map_err(parse_opendal_error)?
-            .map_ok(|e| FileMeta {
-                path: e.path().into(),
-                size: e.metadata().content_length(),
-            })
+            .map_ok(|e| file_meta(e.path(), e.metadata()))
             .map_err(parse_opendal_error))
     }
 
+    /// Lists with opendal's lister, in the order the service returns the entries. Entries are
+    /// only grouped by `/`.
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        if !options.recursive && options.delimiter != DELIMITER {
+            return Err(Error::Unsupported {
+                message: format!("listing with the delimiter {:?}", options.delimiter),
+            });
+        }
+        let dir = match path.as_ref() {
+            "" => String::new(),
+            path => format!("{path}{DELIMITER}"),
+        };
+        let mut lister = self
+            .op
+            .lister_with(&dir)
+            .recursive(options.recursive)
+            .metakey(
+                Metakey::ContentLength
+                    | Metakey::LastModified
+                    | Metakey::Etag
+                    | Metakey::Version
+                    | Metakey::Mode,
+            );
+        if let Some(start_after) = &options.start_after {
+            lister = lister.start_after(start_after.as_ref());
+        }
+        let recursive = options.recursive;
+
+        Ok(lister
+            .await
+            .map_err(parse_opendal_error)?
+            // some services list the directory itself, and recursive listings list directories
+            // along with their entries
+            .try_filter(move |e| ready(e.path() != dir && !(recursive && e.metadata().is_dir())))
+            .map_ok(|e| file_meta(e.path(), e.metadata()))
+            .map_err(parse_opendal_error)
+            .take(options.limit.unwrap_or(usize::MAX)))
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let metadata = self
+            .op
+            .stat(path.as_ref())
+            .await
+            .map_err(parse_opendal_error)?;
+
+        Ok(file_meta(path.as_ref(), &metadata))
+    }
+
     async fn remove(&self, path: &Path) -> Result<(), Error> {
         self.op
             .delete(path.as_ref())
@@ -74,4 +143,25 @@ impl Fs for OpendalFs {
             message: "opendal does not support link file".to_string(),
         })
     }
+
+    /// Renames a file natively if the service supports it, otherwise falls back to a copy
+    /// followed by a delete.
+    ///
+    /// The rename is only reported as atomic by [`Fs::atomic_rename`] for local file systems,
+    /// since most remote services implement renames as a non-atomic copy.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        if self.op.info().full_capability().rename {
+            self.op
+                .rename(from.as_ref(), to.as_ref())
+                .await
+                .map_err(parse_opendal_error)
+        } else {
+            self.copy(from, to).await?;
+            self.remove(from).await
+        }
+    }
+
+    fn atomic_rename(&self) -> bool {
+        self.op.info().scheme() == Scheme::Fs
+    }
 }
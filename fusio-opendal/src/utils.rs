@@ -1,6 +1,6 @@
 use std::io;
 
-use fusio::error::Error;
+use fusio::error::{Error, ErrorKind};
 
 /// Convert an `opendal::Error` into a `fusio::Error`.
 pub fn parse_opendal_error(e: opendal::Error) -> Error {
@@ -12,6 +12,40 @@ pub fn parse_opendal_error(e: opendal::Error) -> Error {
             Error::Io(io::Error::new(io::ErrorKind::PermissionDenied, e))
         }
         opendal::ErrorKind::NotFound => Error::Io(io::Error::new(io::ErrorKind::NotFound, e)),
+        opendal::ErrorKind::ConditionNotMatch => Error::PreconditionFailed {
+            message: e.to_string(),
+        },
+        opendal::ErrorKind::RateLimited => Error::with_kind(ErrorKind::Throttled, e),
+        opendal::ErrorKind::Unsupported => Error::with_kind(ErrorKind::Unsupported, e),
         _ => Error::Other(Box::new(e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fusio::error::ErrorKind;
+
+    use super::parse_opendal_error;
+
+    #[test]
+    fn test_error_kind() {
+        for (kind, expected) in [
+            (opendal::ErrorKind::NotFound, ErrorKind::NotFound),
+            (opendal::ErrorKind::AlreadyExists, ErrorKind::AlreadyExists),
+            (
+                opendal::ErrorKind::PermissionDenied,
+                ErrorKind::PermissionDenied,
+            ),
+            (
+                opendal::ErrorKind::ConditionNotMatch,
+                ErrorKind::PreconditionFailed,
+            ),
+            (opendal::ErrorKind::RateLimited, ErrorKind::Throttled),
+            (opendal::ErrorKind::Unsupported, ErrorKind::Unsupported),
+            (opendal::ErrorKind::Unexpected, ErrorKind::Other),
+        ] {
+            let err = parse_opendal_error(opendal::Error::new(kind, "test"));
+            assert_eq!(err.kind(), expected);
+        }
+    }
+}
@@ -1,11 +1,17 @@
+#[cfg(feature = "bytes")]
+use std::ops::Range;
 use std::{cmp, pin::Pin, sync::Arc};
 
 use fusio_core::{DynWrite, Write};
+#[cfg(feature = "bytes")]
+use futures_core::Stream;
 
 use super::{MaybeSendFuture, MaybeSendStream};
+#[cfg(feature = "bytes")]
+use crate::fs::{GetResult, PutResult};
 use crate::{
     error::Error,
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
     path::Path,
     DynRead, IoBuf, IoBufMut, MaybeSend, MaybeSync, Read,
 };
@@ -30,6 +36,14 @@ impl<'read> Read for Box<dyn DynFile + 'read> {
     async fn size(&self) -> Result<u64, Error> {
         DynRead::size(self.as_ref()).await
     }
+
+    #[cfg(feature = "bytes")]
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend + '_ {
+        DynRead::read_stream(self.as_mut(), range)
+    }
 }
 
 impl<'write> Write for Box<dyn DynFile + 'write> {
@@ -83,11 +97,41 @@ pub trait DynFs: MaybeSend + MaybeSync {
         >,
     >;
 
+    fn list_with<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        options: ListOptions,
+    ) -> Pin<
+        Box<
+            dyn MaybeSendFuture<
+                    Output = Result<
+                        Pin<Box<dyn MaybeSendStream<Item = Result<FileMeta, Error>> + 's>>,
+                        Error,
+                    >,
+                > + 's,
+        >,
+    >;
+
+    fn metadata<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<FileMeta, Error>> + 's>>;
+
     fn remove<'s, 'path: 's>(
         &'s self,
         path: &'path Path,
     ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>>;
 
+    fn remove_batch<'s, 'path: 's>(
+        &'s self,
+        paths: &'path [Path],
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<Vec<Result<(), Error>>, Error>> + 's>>;
+
+    fn remove_dir_all<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>>;
+
     fn copy<'s, 'path: 's>(
         &'s self,
         from: &'path Path,
@@ -99,6 +143,43 @@ pub trait DynFs: MaybeSend + MaybeSync {
         from: &'path Path,
         to: &'path Path,
     ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>>;
+
+    fn rename<'s, 'path: 's>(
+        &'s self,
+        from: &'path Path,
+        to: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>>;
+
+    fn atomic_rename(&self) -> bool;
+
+    /// Returns the kind of the file system this one scopes and the root it scopes it under, if
+    /// this is a [`PrefixFs`](crate::prefix::PrefixFs) returned by
+    /// [`PrefixFs::into_dyn`](crate::prefix::PrefixFs::into_dyn).
+    fn scope(&self) -> Option<(FileSystemTag, &Path)> {
+        None
+    }
+
+    #[cfg(feature = "bytes")]
+    fn put_stream<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        stream: Pin<Box<dyn MaybeSendStream<Item = Result<bytes::Bytes, Error>> + 's>>,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<PutResult, Error>> + 's>>;
+
+    #[cfg(feature = "bytes")]
+    fn put<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<PutResult, Error>> + 's>>;
+
+    #[cfg(feature = "bytes")]
+    fn get<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<GetResult, Error>> + 's>>;
 }
 
 impl<F: Fs> DynFs for F {
@@ -146,6 +227,36 @@ impl<F: Fs> DynFs for F {
         })
     }
 
+    fn list_with<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        options: ListOptions,
+    ) -> Pin<
+        Box<
+            dyn MaybeSendFuture<
+                    Output = Result<
+                        Pin<Box<dyn MaybeSendStream<Item = Result<FileMeta, Error>> + 's>>,
+                        Error,
+                    >,
+                > + 's,
+        >,
+    > {
+        Box::pin(async move {
+            let stream = F::list_with(self, path, options).await?;
+            Ok(Box::pin(stream)
+                as Pin<
+                    Box<dyn MaybeSendStream<Item = Result<FileMeta, Error>>>,
+                >)
+        })
+    }
+
+    fn metadata<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<FileMeta, Error>> + 's>> {
+        Box::pin(F::metadata(self, path))
+    }
+
     fn remove<'s, 'path: 's>(
         &'s self,
         path: &'path Path,
@@ -153,6 +264,20 @@ impl<F: Fs> DynFs for F {
         Box::pin(F::remove(self, path))
     }
 
+    fn remove_batch<'s, 'path: 's>(
+        &'s self,
+        paths: &'path [Path],
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<Vec<Result<(), Error>>, Error>> + 's>> {
+        Box::pin(F::remove_batch(self, paths))
+    }
+
+    fn remove_dir_all<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        Box::pin(F::remove_dir_all(self, path))
+    }
+
     fn copy<'s, 'path: 's>(
         &'s self,
         from: &'path Path,
@@ -168,15 +293,66 @@ impl<F: Fs> DynFs for F {
     ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
         Box::pin(F::link(self, from, to))
     }
+
+    fn rename<'s, 'path: 's>(
+        &'s self,
+        from: &'path Path,
+        to: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        Box::pin(F::rename(self, from, to))
+    }
+
+    fn atomic_rename(&self) -> bool {
+        Fs::atomic_rename(self)
+    }
+
+    #[cfg(feature = "bytes")]
+    fn put_stream<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        stream: Pin<Box<dyn MaybeSendStream<Item = Result<bytes::Bytes, Error>> + 's>>,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<PutResult, Error>> + 's>> {
+        Box::pin(F::put_stream(self, path, stream, options))
+    }
+
+    #[cfg(feature = "bytes")]
+    fn put<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<PutResult, Error>> + 's>> {
+        Box::pin(F::put(self, path, buf, options))
+    }
+
+    #[cfg(feature = "bytes")]
+    fn get<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<GetResult, Error>> + 's>> {
+        Box::pin(F::get(self, path))
+    }
 }
 
+/// Copies `from` in `from_fs` to `to` in `to_fs`, with [`DynFs::copy`] if both are the same kind of
+/// file system and by streaming the content otherwise.
+///
+/// File systems scoped by [`PrefixFs`](crate::prefix::PrefixFs) are only the same kind if their
+/// [`DynFs::scope`]s are equal, i.e. they scope the same kind of file system under the same root.
 pub async fn copy(
     from_fs: &Arc<dyn DynFs>,
     from: &Path,
     to_fs: &Arc<dyn DynFs>,
     to: &Path,
 ) -> Result<(), Error> {
-    if from_fs.file_system() == to_fs.file_system() {
+    let file_system = from_fs.file_system();
+    let same = match (from_fs.scope(), to_fs.scope()) {
+        // the roots of nested scopes are unknown
+        (Some(scope), Some(to_scope)) => scope == to_scope && scope.0 != FileSystemTag::Prefix,
+        _ => file_system == to_fs.file_system() && file_system != FileSystemTag::Prefix,
+    };
+    if same {
         from_fs.copy(from, to).await?;
         return Ok(());
     }
@@ -267,4 +443,40 @@ mod tests {
         let (_, buf) = dyn_file.read_to_end_at(vec![], 0).await;
         assert_eq!(buf.as_slice(), &[24, 9, 24, 0, 34, 19, 34, 10])
     }
+
+    #[cfg(all(
+        feature = "tokio",
+        feature = "bytes",
+        not(feature = "completion-based")
+    ))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dyn_put_stream() {
+        use std::sync::Arc;
+
+        use bytes::Bytes;
+        use futures_util::stream;
+        use tempfile::TempDir;
+
+        use crate::{disk::TokioFs, dynamic::DynFs, fs::OpenOptions, path::Path, Read};
+
+        let tmp_dir = TempDir::new().unwrap();
+        let path = Path::from_filesystem_path(tmp_dir.path().join("put")).unwrap();
+        let fs = Arc::new(TokioFs) as Arc<dyn DynFs>;
+
+        let chunks = stream::iter([Ok(Bytes::from_static(b"hello ")), Ok(Bytes::from("world"))]);
+        let result = fs
+            .put_stream(
+                &path,
+                Box::pin(chunks),
+                OpenOptions::default().create(true).write(true),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.size, 11);
+
+        let mut file = fs.open(&path).await.unwrap();
+        let (result, buf) = file.read_to_end_at(Vec::new(), 0).await;
+        result.unwrap();
+        assert_eq!(buf, b"hello world");
+    }
 }
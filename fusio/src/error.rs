@@ -1,8 +1,17 @@
-pub use fusio_core::error::{BoxedError, Error};
+pub use fusio_core::error::{BoxedError, Error, ErrorKind, KindError};
 
+/// Converts a JavaScript exception into an [`Error`], where the `NotFoundError` of a missing file
+/// or directory is an [`ErrorKind::NotFound`] I/O error.
 #[cfg(all(feature = "opfs", target_arch = "wasm32"))]
 pub(crate) fn wasm_err(js_val: js_sys::wasm_bindgen::JsValue) -> Error {
-    Error::Wasm {
-        message: format!("{js_val:?}"),
+    use js_sys::wasm_bindgen::JsCast;
+
+    let message = format!("{js_val:?}");
+    if js_val
+        .dyn_ref::<js_sys::Error>()
+        .is_some_and(|err| err.name() == "NotFoundError")
+    {
+        return Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, message));
     }
+    Error::Wasm { message }
 }
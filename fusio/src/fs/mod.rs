@@ -3,25 +3,63 @@
 
 mod options;
 
-use std::future::Future;
+use std::{future::Future, pin::pin, time::SystemTime};
+#[cfg(feature = "bytes")]
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
 use futures_core::Stream;
+use futures_util::{stream, StreamExt};
 pub use options::*;
 
 use crate::{error::Error, path::Path, MaybeSend, MaybeSync, Read, Write};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct FileMeta {
     pub path: Path,
     pub size: u64,
+    /// The last time the entry was modified, if the backend reports it.
+    pub last_modified: Option<SystemTime>,
+    /// An opaque identifier of the current content, e.g. the ETag of an S3 object.
+    pub e_tag: Option<String>,
+    /// The version of the entry on backends with versioning enabled.
+    pub version: Option<String>,
+    /// Whether the entry is a directory rather than a file.
+    pub is_dir: bool,
+}
+
+/// The size and ETag of a file written by [`Fs::put`] or [`Fs::put_stream`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PutResult {
+    pub size: u64,
+    /// An opaque identifier of the written content, if the backend reports one.
+    pub e_tag: Option<String>,
+    /// The version of the written content on backends with versioning enabled.
+    pub version: Option<String>,
+}
+
+/// The content of a file read by [`Fs::get`], along with its metadata.
+#[cfg(feature = "bytes")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GetResult {
+    pub bytes: Bytes,
+    pub meta: FileMeta,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum FileSystemTag {
     Local,
+    Memory,
     OPFS,
     // TODO: Remote needs to check whether endpoint and other remote fs are consistent
     S3,
+    /// A file system scoped under a root by [`PrefixFs`](crate::prefix::PrefixFs), whose paths
+    /// are not those of the file system beneath it.
+    Prefix,
 }
 
 pub trait Fs: MaybeSend + MaybeSync {
@@ -49,11 +87,203 @@ pub trait Fs: MaybeSend + MaybeSync {
     ) -> impl Future<Output = Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error>>
            + MaybeSend;
 
+    /// Lists the entries beneath `path` as described by `options`, consistently across file
+    /// systems unlike [`Fs::list`].
+    ///
+    /// File systems that can not list this way fail with [`Error::Unsupported`].
+    fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> impl Future<Output = Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error>>
+           + MaybeSend {
+        let _ = (path, options);
+        async move {
+            Err::<stream::Empty<_>, _>(Error::Unsupported {
+                message: format!("{:?} does not support list_with", self.file_system()),
+            })
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> impl Future<Output = Result<FileMeta, Error>> + MaybeSend;
+
     fn remove(&self, path: &Path) -> impl Future<Output = Result<(), Error>> + MaybeSend;
 
+    /// Removes the files at `paths` and returns the result of each removal, in the order of
+    /// `paths`.
+    ///
+    /// By default the files are removed one after another with [`Fs::remove`]. Backends that
+    /// remove many files per request override this method and fail as a whole if a request
+    /// fails, in which case some of the files may already be removed.
+    fn remove_batch(
+        &self,
+        paths: &[Path],
+    ) -> impl Future<Output = Result<Vec<Result<(), Error>>, Error>> + MaybeSend {
+        async move {
+            let mut results = Vec::with_capacity(paths.len());
+            for path in paths {
+                results.push(self.remove(path).await);
+            }
+
+            Ok(results)
+        }
+    }
+
+    /// Removes every file beneath the directory `path`, along with the directory itself on file
+    /// systems that have directories. A directory that does not exist counts as removed.
+    ///
+    /// By default the files are listed with [`Fs::list_with`] and removed with
+    /// [`Fs::remove_batch`], failing with the error of the first file that could not be removed.
+    fn remove_dir_all(&self, path: &Path) -> impl Future<Output = Result<(), Error>> + MaybeSend {
+        async move {
+            let mut entries = pin!(
+                self.list_with(path, ListOptions::default().recursive(true))
+                    .await?
+            );
+            let mut paths = Vec::new();
+            while let Some(entry) = entries.next().await {
+                let entry = entry?;
+                if !entry.is_dir {
+                    paths.push(entry.path);
+                }
+            }
+
+            self.remove_batch(&paths).await?.into_iter().collect()
+        }
+    }
+
     fn copy(&self, from: &Path, to: &Path) -> impl Future<Output = Result<(), Error>> + MaybeSend;
 
     fn link(&self, from: &Path, to: &Path) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    /// Moves the file at `from` to `to`, replacing `to` if it already exists.
+    ///
+    /// Whether readers can observe an intermediate state, e.g. both paths at once, depends on the
+    /// backend and is reported by [`Fs::atomic_rename`].
+    fn rename(&self, from: &Path, to: &Path)
+        -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    /// Writes the chunks of `stream` to the file at `path`, opened with `options`, e.g.
+    /// `OpenOptions::default().write(true).truncate(true)`, and closes it.
+    ///
+    /// Chunks are written one after another as they arrive, so only the chunks a backend buffers
+    /// are held in memory. The ETag is the one [`Fs::metadata`] reports after the file is closed;
+    /// backends that learn it from the write itself override this method.
+    #[cfg(feature = "bytes")]
+    fn put_stream<S>(
+        &self,
+        path: &Path,
+        stream: S,
+        options: OpenOptions,
+    ) -> impl Future<Output = Result<PutResult, Error>> + MaybeSend
+    where
+        S: Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend,
+    {
+        async move {
+            let mut stream = pin!(stream);
+            let mut file = self.open_options(path, options).await?;
+            let mut size = 0;
+            while let Some(chunk) = stream.next().await {
+                let (result, chunk) = file.write_all(chunk?).await;
+                result?;
+                size += chunk.len() as u64;
+            }
+            file.close().await?;
+            let meta = self.metadata(path).await?;
+
+            Ok(PutResult {
+                size,
+                e_tag: meta.e_tag,
+                version: meta.version,
+            })
+        }
+    }
+
+    /// Replaces the content of the file at `path` with `buf`, so that readers see either the
+    /// previous or the new content in full. `options` contributes the conditions and attributes
+    /// of the write.
+    ///
+    /// By default the content is written to a file next to `path` which is then renamed into
+    /// place, if [`Fs::atomic_rename`] holds and the write has no condition on the current file.
+    /// Otherwise it is written like [`Fs::put_stream`].
+    #[cfg(feature = "bytes")]
+    fn put(
+        &self,
+        path: &Path,
+        buf: Bytes,
+        options: OpenOptions,
+    ) -> impl Future<Output = Result<PutResult, Error>> + MaybeSend {
+        async move {
+            let options = options.truncate(true);
+            // the condition must be checked against the current file, which a rename ignores
+            if options.create_new || options.if_match.is_some() {
+                return self
+                    .put_stream(path, stream::iter([Ok(buf)]), options)
+                    .await;
+            }
+            let options = options.create(true);
+            if !self.atomic_rename() {
+                return self
+                    .put_stream(path, stream::iter([Ok(buf)]), options)
+                    .await;
+            }
+            let size = buf.len() as u64;
+            let staging = staging_path(path);
+            let result = async {
+                let mut file = self.open_options(&staging, options).await?;
+                let (result, _) = file.write_all(buf).await;
+                result?;
+                file.close().await?;
+                self.rename(&staging, path).await
+            }
+            .await;
+            if result.is_err() {
+                // the error of the write matters more than whether it could be cleaned up
+                let _ = self.remove(&staging).await;
+            }
+            result?;
+            let meta = self.metadata(path).await?;
+
+            Ok(PutResult {
+                size,
+                e_tag: meta.e_tag,
+                version: meta.version,
+            })
+        }
+    }
+
+    /// Reads the whole content of the file at `path`, along with its metadata.
+    ///
+    /// By default the file is opened and read to its end, and its metadata fetched afterwards.
+    #[cfg(feature = "bytes")]
+    fn get(&self, path: &Path) -> impl Future<Output = Result<GetResult, Error>> + MaybeSend {
+        async move {
+            let mut file = self.open_options(path, OpenOptions::default()).await?;
+            let (result, buf) = file.read_to_end_at(Vec::new(), 0).await;
+            result?;
+            let meta = self.metadata(path).await?;
+
+            Ok(GetResult {
+                bytes: Bytes::from(buf),
+                meta,
+            })
+        }
+    }
+
+    /// Returns `true` if [`Fs::rename`] replaces `to` and removes `from` in a single atomic step.
+    fn atomic_rename(&self) -> bool {
+        false
+    }
+}
+
+/// Returns a path next to `path` to write its new content to before it is renamed into place.
+#[cfg(feature = "bytes")]
+fn staging_path(path: &Path) -> Path {
+    static STAGED: AtomicU64 = AtomicU64::new(0);
+
+    // the hasher is seeded randomly, so that processes staging the same path do not collide
+    let nonce = RandomState::new().hash_one(STAGED.fetch_add(1, Ordering::Relaxed));
+    Path::from(format!("{path}.{nonce:016x}.tmp"))
 }
 
 #[cfg(test)]
@@ -97,14 +327,19 @@ mod tests {
         let options = S3Options {
             endpoint: "http://localhost:9000/data".into(),
             bucket: "data".to_string(),
-            credential: Some(AwsCredential {
+            credential: Some(Arc::new(AwsCredential {
                 key_id,
                 secret_key,
                 token: None,
-            }),
+            })),
             region: region.into(),
             sign_payload: true,
             checksum: false,
+            encryption: None,
+            part_size: 5 * 1024 * 1024,
+            max_in_flight_parts: 8,
+            read_part_size: 8 * 1024 * 1024,
+            max_in_flight_reads: 8,
         };
 
         let s3_fs = Arc::new(AmazonS3::new(Box::new(client), options));
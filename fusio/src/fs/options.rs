@@ -1,9 +1,19 @@
-#[derive(Debug)]
+use std::collections::BTreeMap;
+
+use crate::{
+    error::Error,
+    path::{Path, DELIMITER},
+};
+
+#[derive(Debug, Clone)]
 pub struct OpenOptions {
     pub read: bool,
     pub write: bool,
     pub create: bool,
     pub truncate: bool,
+    pub create_new: bool,
+    pub if_match: Option<String>,
+    pub attributes: WriteAttributes,
 }
 
 impl Default for OpenOptions {
@@ -13,6 +23,9 @@ impl Default for OpenOptions {
             write: false,
             create: false,
             truncate: false,
+            create_new: false,
+            if_match: None,
+            attributes: WriteAttributes::default(),
         }
     }
 }
@@ -41,4 +54,152 @@ impl OpenOptions {
         self.truncate = truncate;
         self
     }
+
+    /// Only creates the file if nothing exists at the path yet, failing with
+    /// [`Error::PreconditionFailed`](crate::error::Error::PreconditionFailed) otherwise.
+    ///
+    /// Local file systems check the condition when the file is opened, object storages when the
+    /// file is closed and its content is published.
+    pub fn create_new(mut self, create_new: bool) -> Self {
+        self.create_new = create_new;
+        if create_new {
+            self = self.create(true);
+        }
+        self
+    }
+
+    /// Only overwrites the file if its current [`FileMeta::e_tag`](crate::fs::FileMeta::e_tag)
+    /// equals `e_tag`, failing with
+    /// [`Error::PreconditionFailed`](crate::error::Error::PreconditionFailed) otherwise.
+    ///
+    /// Like [`OpenOptions::create_new`], the condition is checked on open by local file systems
+    /// and on close by object storages.
+    pub fn if_match(mut self, e_tag: impl Into<String>) -> Self {
+        self.if_match = Some(e_tag.into());
+        self.write(true)
+    }
+
+    /// Stores `attributes` along with the content written to the file.
+    pub fn attributes(mut self, attributes: WriteAttributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+}
+
+/// Options of [`Fs::list_with`](crate::fs::Fs::list_with).
+///
+/// A listing is ordered by path like the keys of an object storage. Unless it is recursive, it
+/// only holds the direct children of the listed path, and the directories among them are listed
+/// once as entries with [`FileMeta::is_dir`](crate::fs::FileMeta::is_dir) set.
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    /// Lists the files beneath all nested directories instead of the direct children.
+    pub recursive: bool,
+    /// The separator that ends the common prefix of a directory, `/` by default. Ignored by
+    /// recursive listings.
+    pub delimiter: String,
+    /// Only lists the entries whose paths sort after this path.
+    pub start_after: Option<Path>,
+    /// Lists at most this many entries.
+    pub limit: Option<usize>,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            delimiter: DELIMITER.to_string(),
+            start_after: None,
+            limit: None,
+        }
+    }
+}
+
+impl ListOptions {
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Groups the entries by `delimiter` instead of `/`, which only object storages support.
+    pub fn delimiter(mut self, delimiter: impl Into<String>) -> Self {
+        self.delimiter = delimiter.into();
+        self
+    }
+
+    pub fn start_after(mut self, path: Path) -> Self {
+        self.start_after = Some(path);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fails unless the entries are grouped by `/`, for file systems that have no other
+    /// delimiter.
+    pub(crate) fn check_delimiter(&self) -> Result<(), Error> {
+        if self.recursive || self.delimiter == DELIMITER {
+            return Ok(());
+        }
+        Err(Error::Unsupported {
+            message: format!("listing with the delimiter {:?}", self.delimiter),
+        })
+    }
+
+    /// Returns whether the entry at `key`, which ends with the delimiter if it is a directory,
+    /// is listed given [`ListOptions::start_after`]. A directory is listed if any of its entries
+    /// may sort after it.
+    pub(crate) fn lists(&self, key: &str, is_dir: bool) -> bool {
+        match &self.start_after {
+            Some(start_after) => {
+                let start_after = start_after.as_ref();
+                key > start_after || (is_dir && start_after.starts_with(key))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Attributes stored along with the content of a file, such as its content type.
+///
+/// Only object storages keep attributes, e.g. `AmazonS3`; other file systems ignore them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteAttributes {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    /// User-defined metadata, sent as `x-amz-meta-*` headers to S3.
+    pub metadata: BTreeMap<String, String>,
+    /// The storage class of the object, e.g. `INTELLIGENT_TIERING` on S3.
+    pub storage_class: Option<String>,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl WriteAttributes {
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn cache_control(mut self, cache_control: impl Into<String>) -> Self {
+        self.cache_control = Some(cache_control.into());
+        self
+    }
+
+    /// Adds the user-defined metadata `key`, which S3 stores in lower case.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn storage_class(mut self, storage_class: impl Into<String>) -> Self {
+        self.storage_class = Some(storage_class.into());
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
 }
@@ -0,0 +1,536 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use futures_core::Stream;
+
+use super::{BlockIndex, CacheOptions};
+#[cfg(feature = "bytes")]
+use crate::fs::{GetResult, PutResult};
+use crate::{
+    error::Error,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::Path,
+    IoBuf, IoBufMut, MaybeSend, Read, Write,
+};
+
+/// A file system that caches the files of `R` in blocks on the local file system `L`.
+///
+/// Files opened for reading are pinned to the ETag the remote reported when they were opened,
+/// and [`Read::read_exact_at`] is served from cached blocks where possible. A block fetched from
+/// the remote is only cached if the remote still reports that ETag afterwards, so content
+/// written after the file was opened is never cached under the ETag of the version before.
+/// Files of backends that report no ETag are not cached. Writes, removes, copies, links and
+/// renames through this file system drop the cached blocks of every path they change.
+pub struct CachedFs<R, L> {
+    remote: Arc<R>,
+    cache: Arc<DiskCache<L>>,
+}
+
+impl<R: Fs, L: Fs> CachedFs<R, L> {
+    /// Caches the files of `remote` in the directory `root` of `local`.
+    ///
+    /// Blocks left in `root` by a previous process are not reused, so `root` should be a
+    /// directory that is owned by this cache alone.
+    pub fn new(remote: R, local: L, root: Path, options: CacheOptions) -> Self {
+        Self {
+            remote: Arc::new(remote),
+            cache: Arc::new(DiskCache {
+                local,
+                root,
+                index: Mutex::new(BlockIndex::new(options.capacity)),
+                options,
+                root_created: AtomicBool::new(false),
+                temp_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn remote(&self) -> &R {
+        &self.remote
+    }
+
+    pub fn local(&self) -> &L {
+        &self.cache.local
+    }
+
+    /// Returns the number of bytes currently cached on the local file system.
+    pub fn cached_bytes(&self) -> u64 {
+        self.cache.index().used()
+    }
+}
+
+impl<R, L> Clone for CachedFs<R, L> {
+    fn clone(&self) -> Self {
+        Self {
+            remote: self.remote.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<R: Fs + 'static, L: Fs + 'static> Fs for CachedFs<R, L> {
+    type File = CachedFile<R, L>;
+
+    fn file_system(&self) -> FileSystemTag {
+        self.remote.file_system()
+    }
+
+    async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
+        let entry = if options.write {
+            self.cache.invalidate(path).await;
+            None
+        } else {
+            let meta = self.remote.metadata(path).await?;
+            meta.e_tag.map(|e_tag| Entry {
+                e_tag,
+                size: meta.size,
+            })
+        };
+        let write = options.write;
+
+        Ok(CachedFile {
+            inner: self.remote.open_options(path, options).await?,
+            remote: self.remote.clone(),
+            path: path.clone(),
+            cache: self.cache.clone(),
+            entry,
+            write,
+        })
+    }
+
+    async fn create_dir_all(path: &Path) -> Result<(), Error> {
+        R::create_dir_all(path).await
+    }
+
+    async fn list(
+        &self,
+        path: &Path,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        self.remote.list(path).await
+    }
+
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        self.remote.list_with(path, options).await
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        self.remote.metadata(path).await
+    }
+
+    async fn remove(&self, path: &Path) -> Result<(), Error> {
+        self.cache.invalidate(path).await;
+        self.remote.remove(path).await
+    }
+
+    async fn remove_batch(&self, paths: &[Path]) -> Result<Vec<Result<(), Error>>, Error> {
+        for path in paths {
+            self.cache.invalidate(path).await;
+        }
+        self.remote.remove_batch(paths).await
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        self.cache
+            .invalidate_where(|file_path| file_path.prefix_matches(path))
+            .await;
+        self.remote.remove_dir_all(path).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.cache.invalidate(to).await;
+        self.remote.copy(from, to).await
+    }
+
+    async fn link(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.cache.invalidate(to).await;
+        self.remote.link(from, to).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.cache.invalidate(from).await;
+        self.cache.invalidate(to).await;
+        self.remote.rename(from, to).await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put_stream<S>(
+        &self,
+        path: &Path,
+        stream: S,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error>
+    where
+        S: Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend,
+    {
+        self.cache.invalidate(path).await;
+        self.remote.put_stream(path, stream, options).await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put(
+        &self,
+        path: &Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error> {
+        self.cache.invalidate(path).await;
+        self.remote.put(path, buf, options).await
+    }
+
+    /// Reads the whole file from the remote, bypassing the cached blocks.
+    #[cfg(feature = "bytes")]
+    async fn get(&self, path: &Path) -> Result<GetResult, Error> {
+        self.remote.get(path).await
+    }
+
+    fn atomic_rename(&self) -> bool {
+        self.remote.atomic_rename()
+    }
+}
+
+/// Identifies a block of a specific version of a remote file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BlockKey {
+    path: Path,
+    e_tag: String,
+    block: u64,
+}
+
+impl BlockKey {
+    /// The name of the local file the block is stored in.
+    fn file_name(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.path.hash(&mut hasher);
+        self.e_tag.hash(&mut hasher);
+
+        format!("{:016x}-{}", hasher.finish(), self.block)
+    }
+}
+
+/// The blocks cached on the local file system, shared by a [`CachedFs`] and its files.
+struct DiskCache<L> {
+    local: L,
+    root: Path,
+    options: CacheOptions,
+    index: Mutex<BlockIndex<BlockKey>>,
+    root_created: AtomicBool,
+    temp_id: AtomicU64,
+}
+
+impl<L: Fs> DiskCache<L> {
+    fn index(&self) -> MutexGuard<'_, BlockIndex<BlockKey>> {
+        self.index
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn local_path(&self, key: &BlockKey) -> Path {
+        self.root.child(key.file_name())
+    }
+
+    /// Reads a cached block. A block that cannot be read locally, e.g. because it was evicted
+    /// concurrently, is dropped from the index and has to be fetched again.
+    async fn load(&self, key: &BlockKey) -> Option<Vec<u8>> {
+        let (len, _) = self.index().touch(key)?;
+        let result = async {
+            let mut file = self
+                .local
+                .open_options(&self.local_path(key), OpenOptions::default())
+                .await?;
+            let (result, data) = file.read_to_end_at(Vec::new(), 0).await;
+            result.map(|_| data)
+        }
+        .await;
+        match result {
+            Ok(data) if data.len() as u64 == len => Some(data),
+            _ => {
+                self.index().remove(key);
+                None
+            }
+        }
+    }
+
+    /// Stores a block fetched from the remote and returns it. Failures to store the block are
+    /// ignored, as the block can always be fetched again.
+    async fn store(&self, key: BlockKey, data: Vec<u8>) -> Vec<u8> {
+        if data.len() as u64 > self.options.capacity {
+            return data;
+        }
+        let path = self.local_path(&key);
+        // write to a unique temporary file first, so that concurrent readers of the same block
+        // never observe a partially written file
+        let temp = self.root.child(format!(
+            "{}.{}.tmp",
+            key.file_name(),
+            self.temp_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let (result, data) = self.write_block(&temp, data).await;
+        if result.is_err() {
+            let _ = self.local.remove(&temp).await;
+            return data;
+        }
+        if self.local.rename(&temp, &path).await.is_err() {
+            let _ = self.local.remove(&temp).await;
+            return data;
+        }
+        let evicted = self.index().insert(key, data.len() as u64, ());
+        for key in evicted {
+            let _ = self.local.remove(&self.local_path(&key)).await;
+        }
+
+        data
+    }
+
+    async fn write_block(&self, path: &Path, data: Vec<u8>) -> (Result<(), Error>, Vec<u8>) {
+        if !self.root_created.load(Ordering::Relaxed) {
+            if let Err(err) = L::create_dir_all(&self.root).await {
+                return (Err(err), data);
+            }
+            self.root_created.store(true, Ordering::Relaxed);
+        }
+        let mut file = match self
+            .local
+            .open_options(path, OpenOptions::default().create(true).truncate(true))
+            .await
+        {
+            Ok(file) => file,
+            Err(err) => return (Err(err), data),
+        };
+        let (result, data) = file.write_all(data).await;
+        if result.is_err() {
+            return (result, data);
+        }
+
+        (file.close().await, data)
+    }
+
+    /// Drops every cached block of the file at `path`.
+    async fn invalidate(&self, path: &Path) {
+        self.invalidate_where(|file_path| file_path == path).await
+    }
+
+    /// Drops every cached block of the files whose path satisfies `predicate`.
+    async fn invalidate_where(&self, predicate: impl Fn(&Path) -> bool) {
+        let keys = self.index().remove_where(|key| predicate(&key.path));
+        for key in keys {
+            let _ = self.local.remove(&self.local_path(&key)).await;
+        }
+    }
+}
+
+/// The version of a remote file a [`CachedFile`] reads from the cache.
+#[derive(Debug, Clone)]
+struct Entry {
+    e_tag: String,
+    size: u64,
+}
+
+/// A file opened by [`CachedFs`].
+pub struct CachedFile<R: Fs, L> {
+    inner: R::File,
+    remote: Arc<R>,
+    path: Path,
+    cache: Arc<DiskCache<L>>,
+    entry: Option<Entry>,
+    write: bool,
+}
+
+impl<R: Fs, L: Fs> CachedFile<R, L> {
+    pub fn into_inner(self) -> R::File {
+        self.inner
+    }
+
+    /// Fills `dst` with the content at `pos`, which must lie within the cached version.
+    async fn read_cached(&mut self, entry: &Entry, pos: u64, dst: &mut [u8]) -> Result<(), Error> {
+        let block_size = self.cache.options.block_size;
+        let mut offset = 0;
+        while offset < dst.len() {
+            let at = pos + offset as u64;
+            let key = BlockKey {
+                path: self.path.clone(),
+                e_tag: entry.e_tag.clone(),
+                block: at / block_size,
+            };
+            let data = self.read_block(key, entry.size).await?;
+            let start = (at % block_size) as usize;
+            let len = (data.len() - start).min(dst.len() - offset);
+            dst[offset..offset + len].copy_from_slice(&data[start..start + len]);
+            offset += len;
+        }
+
+        Ok(())
+    }
+
+    async fn read_block(&mut self, key: BlockKey, size: u64) -> Result<Vec<u8>, Error> {
+        if let Some(data) = self.cache.load(&key).await {
+            return Ok(data);
+        }
+        let block_size = self.cache.options.block_size;
+        let start = key.block * block_size;
+        let len = block_size.min(size - start) as usize;
+        let (result, data) = self.inner.read_exact_at(vec![0; len], start).await;
+        result?;
+        // the block may have been read from content written after the file was opened
+        match self.remote.metadata(&self.path).await {
+            Ok(meta) if meta.e_tag.as_ref() == Some(&key.e_tag) => {
+                Ok(self.cache.store(key, data).await)
+            }
+            _ => Ok(data),
+        }
+    }
+}
+
+impl<R: Fs, L: Fs + 'static> Read for CachedFile<R, L> {
+    async fn read_exact_at<B: IoBufMut>(&mut self, mut buf: B, pos: u64) -> (Result<(), Error>, B) {
+        let len = buf.bytes_init() as u64;
+        let entry = self
+            .entry
+            .clone()
+            .filter(|entry| pos.checked_add(len).is_some_and(|end| end <= entry.size));
+        let Some(entry) = entry else {
+            return self.inner.read_exact_at(buf, pos).await;
+        };
+        let result = self.read_cached(&entry, pos, buf.as_slice_mut()).await;
+
+        (result, buf)
+    }
+
+    async fn read_to_end_at(&mut self, mut buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
+        let entry = self.entry.clone().filter(|entry| pos <= entry.size);
+        let Some(entry) = entry else {
+            return self.inner.read_to_end_at(buf, pos).await;
+        };
+        let len = buf.len();
+        buf.resize(len + (entry.size - pos) as usize, 0);
+        let result = self.read_cached(&entry, pos, &mut buf[len..]).await;
+        if result.is_err() {
+            buf.truncate(len);
+        }
+
+        (result, buf)
+    }
+
+    async fn size(&self) -> Result<u64, Error> {
+        match &self.entry {
+            Some(entry) => Ok(entry.size),
+            None => self.inner.size().await,
+        }
+    }
+}
+
+impl<R: Fs, L: Fs + 'static> Write for CachedFile<R, L> {
+    async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
+        self.inner.write_all(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush().await
+    }
+
+    /// Closes the file and, if it was opened for writing, drops the blocks cached in the meantime.
+    async fn close(&mut self) -> Result<(), Error> {
+        let result = self.inner.close().await;
+        if self.write {
+            self.cache.invalidate(&self.path).await;
+        }
+
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::CachedFs;
+    use crate::{
+        cache::CacheOptions,
+        fs::{Fs, OpenOptions},
+        impls::{
+            instrumented::{InstrumentedFs, Operation},
+            memory::MemoryFs,
+        },
+        path::Path,
+        Read, Write,
+    };
+
+    async fn write(fs: &impl Fs, path: &Path, content: &'static [u8]) {
+        let mut file = fs
+            .open_options(path, OpenOptions::default().create(true).truncate(true))
+            .await
+            .unwrap();
+        file.write_all(content).await.0.unwrap();
+        file.close().await.unwrap();
+    }
+
+    async fn read(fs: &impl Fs, path: &Path, pos: u64, len: usize) -> Vec<u8> {
+        let mut file = fs.open(path).await.unwrap();
+        let (result, buf) = file.read_exact_at(vec![0; len], pos).await;
+        result.unwrap();
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_cached_fs() {
+        let remote = InstrumentedFs::new(MemoryFs::new());
+        let local = MemoryFs::new();
+        let root = Path::parse("cache").unwrap();
+        let options = CacheOptions::default().block_size(4).capacity(8);
+        let fs = CachedFs::new(remote, local.clone(), root.clone(), options);
+        let remote_reads = || fs.remote().recorder().get(Operation::ReadExactAt).count;
+        let path = Path::parse("cached.file").unwrap();
+
+        write(fs.remote(), &path, b"hello fusio!").await;
+        assert_eq!(read(&fs, &path, 2, 5).await, b"llo f");
+        assert_eq!(remote_reads(), 2);
+        assert_eq!(read(&fs, &path, 0, 8).await, b"hello fu");
+        assert_eq!(remote_reads(), 2);
+        assert_eq!(fs.cached_bytes(), 8);
+
+        // the first block is the least recently used one
+        assert_eq!(read(&fs, &path, 8, 4).await, b"sio!");
+        assert_eq!(read(&fs, &path, 4, 4).await, b"o fu");
+        assert_eq!(remote_reads(), 3);
+        assert_eq!(read(&fs, &path, 0, 4).await, b"hell");
+        assert_eq!(remote_reads(), 4);
+        assert_eq!(local.list(&root).await.unwrap().count().await, 2);
+
+        let mut file = fs.open(&path).await.unwrap();
+        let (result, buf) = file.read_to_end_at(b"x".to_vec(), 6).await;
+        result.unwrap();
+        assert_eq!(buf, b"xfusio!");
+        assert!(file.read_exact_at(vec![0; 4], 10).await.0.is_err());
+
+        // content changed remotely is read under its new ETag
+        write(fs.remote(), &path, b"bye").await;
+        assert_eq!(read(&fs, &path, 0, 3).await, b"bye");
+
+        // blocks of content written after a file was opened are not cached under its ETag
+        let changed = Path::parse("changed.file").unwrap();
+        write(fs.remote(), &changed, b"abc").await;
+        let mut file = fs.open(&changed).await.unwrap();
+        write(fs.remote(), &changed, b"xyz").await;
+        let cached = fs.cached_bytes();
+        let (result, buf) = file.read_exact_at(vec![0; 3], 0).await;
+        result.unwrap();
+        assert_eq!(buf, b"xyz");
+        assert_eq!(fs.cached_bytes(), cached);
+        fs.remove(&changed).await.unwrap();
+
+        write(&fs, &path, b"hello again").await;
+        assert_eq!(fs.cached_bytes(), 0);
+        assert_eq!(read(&fs, &path, 6, 5).await, b"again");
+        fs.remove(&path).await.unwrap();
+        assert_eq!(fs.cached_bytes(), 0);
+        assert_eq!(local.list(&root).await.unwrap().count().await, 0);
+    }
+}
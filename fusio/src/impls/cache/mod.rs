@@ -0,0 +1,183 @@
+//! Read-through caching of file content.
+//!
+//! [`CachedFs`] splits the files of a remote file system into fixed-size blocks and keeps the
+//! blocks it has read in a directory of a local file system, e.g. `TokioFs`. Blocks are keyed by
+//! the path and the ETag of the remote file, so a file that changed remotely is never served from
+//! stale blocks. The total size of the cached blocks is bounded by [`CacheOptions::capacity`]; the
+//! least recently used blocks are evicted first.
+//!
+//! [`CachedRead`] caches the blocks of any [`Read`](crate::Read) in process memory instead, in a
+//! [`BlockCache`] that is shared by every handle reading through it.
+
+#[cfg(feature = "fs")]
+mod fs;
+mod read;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+#[cfg(feature = "fs")]
+pub use fs::{CachedFile, CachedFs};
+pub use read::{BlockCache, CacheStats, CachedRead};
+
+/// Configures the block size and the capacity of a [`CachedFs`] or a [`BlockCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheOptions {
+    pub block_size: u64,
+    pub capacity: u64,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            block_size: 1024 * 1024,
+            capacity: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl CacheOptions {
+    /// Sets the size of the blocks files are cached in. Values below one are treated as one.
+    pub fn block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of bytes kept on the local file system.
+    pub fn capacity(mut self, capacity: u64) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// Tracks the cached blocks, along with a value of type `V` for each, and the order in which
+/// they were used.
+#[derive(Debug)]
+pub(crate) struct BlockIndex<K, V = ()> {
+    capacity: u64,
+    used: u64,
+    tick: u64,
+    blocks: HashMap<K, Block<V>>,
+    lru: BTreeMap<u64, K>,
+}
+
+#[derive(Debug)]
+struct Block<V> {
+    len: u64,
+    last_use: u64,
+    value: V,
+}
+
+impl<K: Clone + Eq + Hash, V> BlockIndex<K, V> {
+    pub(crate) fn new(capacity: u64) -> Self {
+        Self {
+            capacity,
+            used: 0,
+            tick: 0,
+            blocks: HashMap::new(),
+            lru: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// Marks `key` as the most recently used block and returns its length and value, if it is
+    /// cached.
+    pub(crate) fn touch(&mut self, key: &K) -> Option<(u64, &V)> {
+        self.tick += 1;
+        let block = self.blocks.get_mut(key)?;
+        let key = self
+            .lru
+            .remove(&block.last_use)
+            .expect("cached block must be in the lru order");
+        block.last_use = self.tick;
+        self.lru.insert(self.tick, key);
+
+        Some((block.len, &block.value))
+    }
+
+    /// Adds a block of `len` bytes and returns the blocks evicted to make room for it. A block
+    /// larger than the capacity is evicted right away.
+    pub(crate) fn insert(&mut self, key: K, len: u64, value: V) -> Vec<K> {
+        if self.touch(&key).is_some() {
+            return Vec::new();
+        }
+        if len > self.capacity {
+            return vec![key];
+        }
+        let mut evicted = Vec::new();
+        while self.used + len > self.capacity {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(block) = self.blocks.remove(&oldest) {
+                self.used -= block.len;
+            }
+            evicted.push(oldest);
+        }
+        self.used += len;
+        self.blocks.insert(
+            key.clone(),
+            Block {
+                len,
+                last_use: self.tick,
+                value,
+            },
+        );
+        self.lru.insert(self.tick, key);
+
+        evicted
+    }
+
+    #[cfg(feature = "fs")]
+    pub(crate) fn remove(&mut self, key: &K) -> bool {
+        let Some(block) = self.blocks.remove(key) else {
+            return false;
+        };
+        self.lru.remove(&block.last_use);
+        self.used -= block.len;
+
+        true
+    }
+
+    /// Removes every block whose key matches `predicate` and returns them.
+    #[cfg(feature = "fs")]
+    pub(crate) fn remove_where(&mut self, predicate: impl Fn(&K) -> bool) -> Vec<K> {
+        let keys = self
+            .blocks
+            .keys()
+            .filter(|key| predicate(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in &keys {
+            self.remove(key);
+        }
+
+        keys
+    }
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::BlockIndex;
+
+    #[test]
+    fn block_index_evicts_least_recently_used() {
+        let mut index = BlockIndex::new(10);
+        assert!(index.insert(("a", 0), 4, ()).is_empty());
+        assert!(index.insert(("a", 1), 4, ()).is_empty());
+        assert_eq!(index.touch(&("a", 0)), Some((4, &())));
+
+        assert_eq!(index.insert(("b", 0), 4, ()), vec![("a", 1)]);
+        assert_eq!(index.used(), 8);
+        assert_eq!(index.touch(&("a", 1)), None);
+        assert_eq!(index.insert(("c", 0), 11, ()), vec![("c", 0)]);
+
+        assert_eq!(index.remove_where(|(file, _)| *file == "a"), vec![("a", 0)]);
+        assert_eq!(index.used(), 4);
+    }
+}
@@ -0,0 +1,246 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard,
+};
+
+use super::{BlockIndex, CacheOptions};
+use crate::{error::Error, IoBufMut, Read};
+
+type MemoryKey = (Arc<str>, u64);
+
+/// Blocks of file content kept in process memory, shared by every [`CachedRead`] it is passed to.
+///
+/// The total size of the blocks is bounded by [`CacheOptions::capacity`]; the least recently used
+/// blocks are evicted first.
+#[derive(Debug)]
+pub struct BlockCache {
+    block_size: u64,
+    blocks: Mutex<BlockIndex<MemoryKey, Vec<u8>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BlockCache {
+    pub fn new(options: CacheOptions) -> Self {
+        Self {
+            block_size: options.block_size.max(1),
+            blocks: Mutex::new(BlockIndex::new(options.capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of blocks read from and missing in the cache so far.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the number of bytes currently cached.
+    pub fn cached_bytes(&self) -> u64 {
+        self.blocks().used()
+    }
+
+    fn blocks(&self) -> MutexGuard<'_, BlockIndex<MemoryKey, Vec<u8>>> {
+        self.blocks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The hits and misses of a [`BlockCache`], counted in blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// A reader that serves reads of `R` from a [`BlockCache`].
+///
+/// Reads are aligned to the block size of the cache. The blocks missing for a read are fetched
+/// with a single read of `R`, which also re-reads cached blocks that lie between missing ones.
+pub struct CachedRead<R> {
+    inner: R,
+    cache: Arc<BlockCache>,
+    key: Arc<str>,
+    size: u64,
+}
+
+impl<R: Read> CachedRead<R> {
+    /// Wraps `inner`, whose content is identified by `key`, e.g. its path and ETag. Readers with
+    /// equal keys share the blocks in `cache`, so the key must change whenever the content does.
+    pub async fn new(
+        inner: R,
+        cache: Arc<BlockCache>,
+        key: impl Into<String>,
+    ) -> Result<Self, Error> {
+        let size = inner.size().await?;
+
+        Ok(Self {
+            inner,
+            cache,
+            key: Arc::from(key.into()),
+            size,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Fills `dst` with the content at `pos`, which must lie within the file.
+    async fn read_cached(&mut self, pos: u64, dst: &mut [u8]) -> Result<(), Error> {
+        if dst.is_empty() {
+            return Ok(());
+        }
+        let block_size = self.cache.block_size;
+        let end = pos + dst.len() as u64;
+        let first = pos / block_size;
+        let last = (end - 1) / block_size;
+
+        let mut missing: Option<(u64, u64)> = None;
+        let mut misses = 0;
+        {
+            let mut blocks = self.cache.blocks();
+            for block in first..=last {
+                match blocks.touch(&(self.key.clone(), block)) {
+                    Some((_, data)) => copy_block(data, block * block_size, pos, dst),
+                    None => {
+                        misses += 1;
+                        missing = Some((missing.map_or(block, |(from, _)| from), block));
+                    }
+                }
+            }
+        }
+        self.cache
+            .hits
+            .fetch_add(last - first + 1 - misses, Ordering::Relaxed);
+        let Some((from, to)) = missing else {
+            return Ok(());
+        };
+        self.cache.misses.fetch_add(misses, Ordering::Relaxed);
+
+        let start = from * block_size;
+        let len = ((to + 1) * block_size).min(self.size) - start;
+        let (result, data) = self.inner.read_exact_at(vec![0; len as usize], start).await;
+        result?;
+        copy_block(&data, start, pos, dst);
+
+        let mut blocks = self.cache.blocks();
+        for (block, chunk) in (from..).zip(data.chunks(block_size as usize)) {
+            blocks.insert(
+                (self.key.clone(), block),
+                chunk.len() as u64,
+                chunk.to_vec(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Copies the overlap of `data`, the content at `data_pos`, and `dst`, the content at `pos`.
+fn copy_block(data: &[u8], data_pos: u64, pos: u64, dst: &mut [u8]) {
+    let start = data_pos.max(pos);
+    let end = (data_pos + data.len() as u64).min(pos + dst.len() as u64);
+    if start >= end {
+        return;
+    }
+    let len = (end - start) as usize;
+    let src = (start - data_pos) as usize;
+    let at = (start - pos) as usize;
+    dst[at..at + len].copy_from_slice(&data[src..src + len]);
+}
+
+impl<R: Read> Read for CachedRead<R> {
+    async fn read_exact_at<B: IoBufMut>(&mut self, mut buf: B, pos: u64) -> (Result<(), Error>, B) {
+        let len = buf.bytes_init() as u64;
+        if pos.checked_add(len).is_none_or(|end| end > self.size) {
+            return self.inner.read_exact_at(buf, pos).await;
+        }
+        let result = self.read_cached(pos, buf.as_slice_mut()).await;
+
+        (result, buf)
+    }
+
+    async fn read_to_end_at(&mut self, mut buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
+        if pos > self.size {
+            return self.inner.read_to_end_at(buf, pos).await;
+        }
+        let len = buf.len();
+        buf.resize(len + (self.size - pos) as usize, 0);
+        let result = self.read_cached(pos, &mut buf[len..]).await;
+        if result.is_err() {
+            buf.truncate(len);
+        }
+
+        (result, buf)
+    }
+
+    async fn size(&self) -> Result<u64, Error> {
+        Ok(self.size)
+    }
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{BlockCache, CacheStats, CachedRead};
+    use crate::{
+        cache::CacheOptions,
+        fs::{Fs, OpenOptions},
+        impls::{
+            instrumented::{InstrumentedFs, Operation},
+            memory::MemoryFs,
+        },
+        path::Path,
+        Read, Write,
+    };
+
+    #[tokio::test]
+    async fn test_cached_read() {
+        let fs = InstrumentedFs::new(MemoryFs::new());
+        let path = Path::parse("cached.file").unwrap();
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create(true))
+            .await
+            .unwrap();
+        file.write_all(&b"hello fusio!"[..]).await.0.unwrap();
+        file.close().await.unwrap();
+
+        let cache = Arc::new(BlockCache::new(
+            CacheOptions::default().block_size(4).capacity(12),
+        ));
+        let remote_reads = || fs.recorder().get(Operation::ReadExactAt).count;
+        let mut reader = CachedRead::new(fs.open(&path).await.unwrap(), cache.clone(), "a")
+            .await
+            .unwrap();
+
+        let (result, buf) = reader.read_exact_at(vec![0; 2], 5).await;
+        result.unwrap();
+        assert_eq!(buf, b" f");
+        assert_eq!(remote_reads(), 1);
+
+        // the cached middle block is read again to fetch both misses at once
+        let (result, buf) = reader.read_exact_at(vec![0; 12], 0).await;
+        result.unwrap();
+        assert_eq!(buf, b"hello fusio!");
+        assert_eq!(remote_reads(), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 3 });
+        assert_eq!(cache.cached_bytes(), 12);
+
+        // handles with the same key share the blocks
+        let mut other = CachedRead::new(fs.open(&path).await.unwrap(), cache.clone(), "a")
+            .await
+            .unwrap();
+        let (result, buf) = other.read_to_end_at(b"x".to_vec(), 3).await;
+        result.unwrap();
+        assert_eq!(buf, b"xlo fusio!");
+        assert_eq!(remote_reads(), 2);
+        assert_eq!(cache.stats(), CacheStats { hits: 4, misses: 3 });
+        assert!(other.read_exact_at(vec![0; 4], 10).await.0.is_err());
+    }
+}
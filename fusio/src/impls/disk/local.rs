@@ -0,0 +1,197 @@
+//! Helpers shared by the file systems backed by the local disk.
+
+use std::{
+    fs, io,
+    path::{Path as LocalPath, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::walk::{DirEntry, SortedWalk, Step};
+use crate::{
+    error::Error,
+    fs::{FileMeta, ListOptions, OpenOptions},
+    path::Path,
+};
+
+pub(crate) fn local_file_meta(path: Path, metadata: &fs::Metadata) -> FileMeta {
+    FileMeta {
+        path,
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        last_modified: metadata.modified().ok(),
+        e_tag: if metadata.is_dir() {
+            None
+        } else {
+            local_e_tag(metadata)
+        },
+        version: None,
+        is_dir: metadata.is_dir(),
+    }
+}
+
+/// Derives an ETag of a local file from its modification time and length.
+///
+/// The modification time only advances with the clock of the file system, so a write that keeps
+/// the length within one of its ticks would keep the ETag. Writes through
+/// [`OpenOptions::if_match`] therefore advance it on close with [`advance_modified`]; other
+/// writes may still keep it.
+pub(crate) fn local_e_tag(metadata: &fs::Metadata) -> Option<String> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    Some(format!("{:x}-{:x}", modified.as_nanos(), metadata.len()))
+}
+
+/// Maps the error of opening a file with `create_new` to [`Error::PreconditionFailed`] if the
+/// file already exists.
+pub(crate) fn create_new_error(path: &LocalPath, err: io::Error) -> Error {
+    if err.kind() == io::ErrorKind::AlreadyExists {
+        Error::PreconditionFailed {
+            message: format!("file already exists: {}", path.display()),
+        }
+    } else {
+        Error::Io(err)
+    }
+}
+
+/// Removes the directory `path` and everything beneath it, see
+/// [`Fs::remove_dir_all`](crate::fs::Fs::remove_dir_all).
+pub(crate) fn local_remove_dir_all(path: &LocalPath) -> Result<(), Error> {
+    match fs::remove_dir_all(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Moves the modification time of `file`, which was opened with [`OpenOptions::if_match`] while
+/// modified at `matched`, past `matched` if the writes left it there, so that the ETag the
+/// writer matched is not observed again.
+pub(crate) fn advance_modified(file: &fs::File, matched: SystemTime) -> Result<(), Error> {
+    if file.metadata()?.modified()? <= matched {
+        file.set_modified(matched + Duration::from_nanos(1))?;
+    }
+
+    Ok(())
+}
+
+/// Opens an existing file for writing if its ETag equals `e_tag`, and returns it along with its
+/// modification time, which the file must pass to [`advance_modified`] on close.
+///
+/// The file is locked exclusively before the comparison and stays locked until it is closed, so
+/// writers opening the same file with [`OpenOptions::if_match`] are serialized and only the first
+/// of them observes a matching ETag.
+///
+/// This blocks until the lock is acquired, so it must not run on the thread of a runtime whose
+/// tasks may hold the lock; use `try_open_if_match` there.
+#[cfg(feature = "tokio")]
+pub(crate) fn open_if_match(
+    path: &LocalPath,
+    options: &OpenOptions,
+    e_tag: &str,
+) -> Result<(fs::File, SystemTime), Error> {
+    open_locked_if_match(path, options, e_tag, |file| Ok(file.lock()?))
+}
+
+/// Like [`open_if_match`], but fails with [`Error::PreconditionFailed`] instead of waiting if
+/// another writer holds the lock, as that writer is about to change the ETag anyway.
+#[cfg(any(feature = "monoio", all(feature = "tokio-uring", target_os = "linux")))]
+pub(crate) fn try_open_if_match(
+    path: &LocalPath,
+    options: &OpenOptions,
+    e_tag: &str,
+) -> Result<(fs::File, SystemTime), Error> {
+    open_locked_if_match(path, options, e_tag, |file| match file.try_lock() {
+        Ok(()) => Ok(()),
+        Err(fs::TryLockError::WouldBlock) => Err(Error::PreconditionFailed {
+            message: format!("{} is being written by another writer", path.display()),
+        }),
+        Err(fs::TryLockError::Error(err)) => Err(err.into()),
+    })
+}
+
+fn open_locked_if_match(
+    path: &LocalPath,
+    options: &OpenOptions,
+    e_tag: &str,
+    lock: impl FnOnce(&fs::File) -> Result<(), Error>,
+) -> Result<(fs::File, SystemTime), Error> {
+    let file = fs::OpenOptions::new()
+        .read(options.read)
+        .write(true)
+        .append(!options.truncate)
+        .open(path)?;
+    lock(&file)?;
+
+    let metadata = file.metadata()?;
+    if local_e_tag(&metadata).as_deref() != Some(e_tag) {
+        return Err(Error::PreconditionFailed {
+            message: format!("ETag of {} does not match {}", path.display(), e_tag),
+        });
+    }
+    if options.truncate {
+        file.set_len(0)?;
+    }
+
+    Ok((file, metadata.modified()?))
+}
+
+/// The entries beneath a local directory as a [`SortedWalk`].
+pub(crate) struct LocalWalk {
+    walk: SortedWalk<LocalEntry>,
+}
+
+struct LocalEntry {
+    local_path: PathBuf,
+    metadata: fs::Metadata,
+}
+
+impl LocalWalk {
+    pub(crate) fn new(dir: &LocalPath, options: ListOptions) -> Result<Self, Error> {
+        options.check_delimiter()?;
+        let entries = match read_dir(dir) {
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
+            entries => Some(entries?),
+        };
+
+        Ok(Self {
+            walk: SortedWalk::new(entries, options),
+        })
+    }
+}
+
+impl Iterator for LocalWalk {
+    type Item = Result<FileMeta, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.walk.step()? {
+                Step::List(entry) => {
+                    return Some(Ok(local_file_meta(entry.path, &entry.handle.metadata)))
+                }
+                Step::Descend(entry) => match read_dir(&entry.handle.local_path) {
+                    Ok(entries) => self.walk.descend(entries),
+                    Err(err) => return Some(Err(err)),
+                },
+            }
+        }
+    }
+}
+
+fn read_dir(dir: &LocalPath) -> Result<Vec<DirEntry<LocalEntry>>, Error> {
+    dir.read_dir()?
+        .map(|entry| {
+            let entry = entry?;
+            let local_path = entry.path();
+            let path = Path::from_filesystem_path(&local_path)
+                .map_err(|err| Error::Path(Box::new(err)))?;
+            let metadata = entry.metadata()?;
+
+            Ok(DirEntry::new(
+                path,
+                metadata.is_dir(),
+                LocalEntry {
+                    local_path,
+                    metadata,
+                },
+            ))
+        })
+        .collect()
+}
@@ -46,3 +46,24 @@ cfg_if::cfg_if! {
         pub type LocalFs = OPFS;
     }
 }
+
+#[cfg(all(
+    feature = "fs",
+    any(
+        feature = "tokio",
+        feature = "monoio",
+        all(feature = "tokio-uring", target_os = "linux")
+    )
+))]
+pub(crate) mod local;
+
+#[cfg(all(
+    feature = "fs",
+    any(
+        feature = "tokio",
+        feature = "monoio",
+        all(feature = "tokio-uring", target_os = "linux"),
+        all(feature = "opfs", target_arch = "wasm32")
+    )
+))]
+pub(crate) mod walk;
@@ -2,11 +2,15 @@ use std::{fs, fs::create_dir_all, io::ErrorKind};
 
 use async_stream::stream;
 use futures_core::Stream;
+use futures_util::stream;
 
 use super::MonoioFile;
 use crate::{
+    disk::local::{
+        create_new_error, local_file_meta, local_remove_dir_all, try_open_if_match, LocalWalk,
+    },
     error::Error,
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
     path::{path_to_local, Path},
 };
 
@@ -21,6 +25,30 @@ impl Fs for MonoIoFs {
 
     async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
         let local_path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+        if options.create_new {
+            if let Some(parent_path) = local_path.parent() {
+                create_dir_all(parent_path)?;
+            }
+            let file = monoio::fs::OpenOptions::new()
+                .read(options.read)
+                .write(true)
+                .create_new(true)
+                .open(&local_path)
+                .await
+                .map_err(|err| create_new_error(&local_path, err))?;
+
+            return Ok(MonoioFile::new(file, 0));
+        }
+        if let Some(e_tag) = &options.if_match {
+            let (file, matched) = try_open_if_match(&local_path, &options, e_tag)?;
+            let pos = file.metadata()?.len();
+
+            return Ok(MonoioFile {
+                file: Some(monoio::fs::File::from_std(file)?),
+                pos,
+                matched: Some(matched),
+            });
+        }
         if !local_path.exists() {
             if options.create {
                 if let Some(parent_path) = local_path.parent() {
@@ -66,20 +94,43 @@ impl Fs for MonoIoFs {
         Ok(stream! {
             for entry in dir {
                 let entry = entry?;
-                yield Ok(FileMeta {
-                    path: Path::from_filesystem_path(entry.path()).map_err(|err| Error::Path(err.into()))?,
-                    size: entry.metadata()?.len()
-                });
+                yield Ok(local_file_meta(
+                    Path::from_filesystem_path(entry.path()).map_err(|err| Error::Path(err.into()))?,
+                    &entry.metadata()?,
+                ));
             }
         })
     }
 
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        let path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+
+        Ok(stream::iter(LocalWalk::new(&path, options)?))
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let local_path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+        let metadata = fs::metadata(local_path)?;
+
+        Ok(local_file_meta(path.clone(), &metadata))
+    }
+
     async fn remove(&self, path: &Path) -> Result<(), Error> {
         let path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
 
         Ok(fs::remove_file(path)?)
     }
 
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        let path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+
+        local_remove_dir_all(&path)
+    }
+
     async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
         let from = path_to_local(from).map_err(|err| Error::Path(err.into()))?;
         let to = path_to_local(to).map_err(|err| Error::Path(err.into()))?;
@@ -97,4 +148,18 @@ impl Fs for MonoIoFs {
 
         Ok(())
     }
+
+    /// Renames the file with rename(2), which is atomic within a single file system.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let from = path_to_local(from).map_err(|err| Error::Path(err.into()))?;
+        let to = path_to_local(to).map_err(|err| Error::Path(err.into()))?;
+
+        monoio::spawn(async move { fs::rename(&from, &to) }).await?;
+
+        Ok(())
+    }
+
+    fn atomic_rename(&self) -> bool {
+        true
+    }
 }
@@ -1,8 +1,17 @@
 #[cfg(feature = "fs")]
 pub mod fs;
 
+#[cfg(feature = "fs")]
+use std::{
+    mem::ManuallyDrop,
+    os::fd::{AsRawFd, FromRawFd},
+    time::SystemTime,
+};
+
 use monoio::fs::File;
 
+#[cfg(feature = "fs")]
+use crate::disk::local::advance_modified;
 use crate::{error::Error, IoBuf, IoBufMut, Read, Write};
 
 #[repr(transparent)]
@@ -41,14 +50,15 @@ where
 pub struct MonoioFile {
     file: Option<File>,
     pos: u64,
+    /// The modification time of a file opened with
+    /// [`OpenOptions::if_match`](crate::fs::OpenOptions::if_match), advanced on close.
+    #[cfg(feature = "fs")]
+    matched: Option<SystemTime>,
 }
 
 impl From<File> for MonoioFile {
     fn from(file: File) -> Self {
-        Self {
-            file: Some(file),
-            pos: 0,
-        }
+        Self::new(file, 0)
     }
 }
 
@@ -57,6 +67,8 @@ impl MonoioFile {
         Self {
             file: Some(file),
             pos,
+            #[cfg(feature = "fs")]
+            matched: None,
         }
     }
 }
@@ -79,7 +91,15 @@ impl Write for MonoioFile {
     }
 
     async fn close(&mut self) -> Result<(), Error> {
-        File::close(self.file.take().expect("close file twice")).await?;
+        let file = self.file.take().expect("close file twice");
+        #[cfg(feature = "fs")]
+        if let Some(matched) = self.matched.take() {
+            // the lock is released once the file is closed, so its time is advanced before
+            let std_file =
+                ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(file.as_raw_fd()) });
+            advance_modified(&std_file, matched)?;
+        }
+        File::close(file).await?;
         Ok(())
     }
 }
@@ -1,3 +1,5 @@
+use std::time::{Duration, UNIX_EPOCH};
+
 use async_stream::stream;
 use futures_core::Stream;
 use futures_util::StreamExt;
@@ -7,7 +9,7 @@ use js_sys::{
 };
 use wasm_bindgen_futures::{stream::JsStream, JsFuture};
 use web_sys::{
-    FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
+    File, FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
     FileSystemGetFileOptions, FileSystemRemoveOptions,
 };
 
@@ -16,10 +18,14 @@ use super::OPFSFile;
 #[cfg(feature = "sync")]
 use crate::disk::OPFSSyncFile;
 use crate::{
-    disk::opfs::{promise, storage},
-    error::{wasm_err, Error},
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
+    disk::{
+        opfs::{promise, storage},
+        walk::{DirEntry, SortedWalk, Step},
+    },
+    error::{wasm_err, Error, ErrorKind},
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
     path::Path,
+    Read, Write,
 };
 
 /// [OPFS](https://developer.mozilla.org/en-US/docs/Web/API/File_System_API/Origin_private_file_system) backend
@@ -40,7 +46,15 @@ impl Fs for OPFS {
     /// It is not permitted to use paths that temporarily step outside the sandbox with something
     /// like `../foo` or `./bar`. It is recommended to call [`Path::from_opfs_path`] or
     /// [`Path::parse`]
+    ///
+    /// [`OpenOptions::create_new`] is checked before the file is created, which is not atomic, and
+    /// [`OpenOptions::if_match`] is not supported since OPFS files have no ETag.
     async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
+        if options.if_match.is_some() {
+            return Err(Error::Unsupported {
+                message: "opfs does not support conditional writes with if_match".to_string(),
+            });
+        }
         let segments: Vec<&str> = path.as_ref().trim_matches('/').split("/").collect();
 
         if segments.len() == 1 && segments[0].is_empty() {
@@ -54,6 +68,15 @@ impl Fs for OPFS {
         let parent = Self::access_parent_dir(path, &dir_options).await?;
 
         let file_name = segments.last().unwrap();
+        if options.create_new
+            && promise::<FileSystemFileHandle>(parent.get_file_handle(file_name))
+                .await
+                .is_ok()
+        {
+            return Err(Error::PreconditionFailed {
+                message: format!("file already exists: {}", path),
+            });
+        }
         let option = FileSystemGetFileOptions::new();
         option.set_create(options.create);
 
@@ -102,11 +125,83 @@ impl Fs for OPFS {
 
         Ok(stream! {
             for entry in entries {
-                yield Ok(FileMeta{ path: path.child(entry), size: 0 })
+                yield Ok(FileMeta{ path: path.child(entry), ..Default::default() })
             }
         })
     }
 
+    /// Walks the directories in the order of the paths of their entries, reading and sorting the
+    /// entries of a directory once the walk reaches it. A directory that does not exist has no
+    /// entries.
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        options.check_delimiter()?;
+        let dir_options = FileSystemGetDirectoryOptions::new();
+        dir_options.set_create(false);
+        let entries = match Self::access_dir(path, &dir_options).await {
+            Ok(dir) => Some(read_dir(path, &dir).await?),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        let mut walk = SortedWalk::new(entries, options);
+
+        Ok(stream! {
+            while let Some(step) = walk.step() {
+                match step {
+                    Step::List(entry) => match entry.handle.dyn_into::<FileSystemFileHandle>() {
+                        Ok(handle) => {
+                            yield file_meta(entry.path, &handle).await;
+                        }
+                        Err(_) => {
+                            yield Ok(FileMeta {
+                                path: entry.path,
+                                is_dir: true,
+                                ..Default::default()
+                            });
+                        }
+                    },
+                    Step::Descend(entry) => {
+                        let dir = entry
+                            .handle
+                            .dyn_into::<FileSystemDirectoryHandle>()
+                            .map_err(|_| Error::CastError)?;
+                        walk.descend(read_dir(&entry.path, &dir).await?);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Returns the metadata of a file or a directory.
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let name = path.as_ref().trim_matches('/').split("/").last().unwrap();
+        if name.is_empty() {
+            return Ok(FileMeta {
+                path: path.clone(),
+                is_dir: true,
+                ..Default::default()
+            });
+        }
+        let dir_options = FileSystemGetDirectoryOptions::new();
+        dir_options.set_create(false);
+        let parent = Self::access_parent_dir(path, &dir_options).await?;
+
+        match promise::<FileSystemFileHandle>(parent.get_file_handle(name)).await {
+            Ok(file_handle) => file_meta(path.clone(), &file_handle).await,
+            Err(_) => {
+                promise::<FileSystemDirectoryHandle>(parent.get_directory_handle(name)).await?;
+                Ok(FileMeta {
+                    path: path.clone(),
+                    is_dir: true,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
     /// Recursively removes an entry from OPFS. See more detail in [removeEntry](https://developer.mozilla.org/en-US/docs/Web/API/FileSystemDirectoryHandle/removeEntry)
     async fn remove(&self, path: &Path) -> Result<(), Error> {
         let dir_options = FileSystemGetDirectoryOptions::new();
@@ -122,6 +217,15 @@ impl Fs for OPFS {
         Ok(())
     }
 
+    /// Removes the directory and everything beneath it with a recursive
+    /// [removeEntry](https://developer.mozilla.org/en-US/docs/Web/API/FileSystemDirectoryHandle/removeEntry).
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        match self.remove(path).await {
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
     async fn copy(&self, _: &Path, _: &Path) -> Result<(), Error> {
         Err(Error::Unsupported {
             message: "opfs does not support copy file".to_string(),
@@ -133,6 +237,32 @@ impl Fs for OPFS {
             message: "opfs does not support link file".to_string(),
         })
     }
+
+    /// Renames a file by writing its content to `to` and then removing `from`.
+    ///
+    /// OPFS has no rename primitive, so the rename is not atomic: both files exist until `from` is
+    /// removed.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let mut from_file = self.open_options(from, OpenOptions::default()).await?;
+        let (result, buf) = from_file.read_to_end_at(Vec::new(), 0).await;
+        result?;
+        from_file.close().await?;
+
+        let mut to_file = self
+            .open_options(
+                to,
+                OpenOptions::default()
+                    .create(true)
+                    .write(true)
+                    .truncate(true),
+            )
+            .await?;
+        let (result, _) = to_file.write_all(buf).await;
+        result?;
+        to_file.close().await?;
+
+        self.remove(from).await
+    }
 }
 
 impl OPFS {
@@ -188,3 +318,42 @@ impl OPFS {
         Ok(parent)
     }
 }
+
+/// Reads the entries of the directory `dir` at `path`, each with its `FileSystemFileHandle` or
+/// `FileSystemDirectoryHandle`.
+async fn read_dir(
+    path: &Path,
+    dir: &FileSystemDirectoryHandle,
+) -> Result<Vec<DirEntry<JsValue>>, Error> {
+    let mut entries = Vec::new();
+    let mut stream = JsStream::from(dir.entries());
+    while let Some(entry) = stream.next().await {
+        let entry = entry
+            .map_err(wasm_err)?
+            .dyn_into::<Array>()
+            .map_err(|_| Error::CastError)?;
+        let name: String = entry
+            .get(0)
+            .dyn_into::<JsString>()
+            .map_err(|_| Error::CastError)?
+            .into();
+        let handle = entry.get(1);
+        let is_dir = handle.is_instance_of::<FileSystemDirectoryHandle>();
+        entries.push(DirEntry::new(path.child(name), is_dir, handle));
+    }
+
+    Ok(entries)
+}
+
+async fn file_meta(path: Path, file_handle: &FileSystemFileHandle) -> Result<FileMeta, Error> {
+    let file = promise::<File>(file_handle.get_file()).await?;
+
+    Ok(FileMeta {
+        path,
+        size: file.size().round() as u64,
+        last_modified: Some(
+            UNIX_EPOCH + Duration::from_millis(file.last_modified().round() as u64),
+        ),
+        ..Default::default()
+    })
+}
@@ -8,12 +8,20 @@ use tokio::{
 };
 
 use crate::{
-    disk::tokio::TokioFile,
+    disk::{
+        local::{
+            create_new_error, local_file_meta, local_remove_dir_all, open_if_match, LocalWalk,
+        },
+        tokio::TokioFile,
+    },
     error::Error,
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
     path::{path_to_local, Path},
 };
 
+/// The number of entries [`TokioFs::list_with`] reads per task on the blocking thread pool.
+const LIST_PAGE: usize = 1000;
+
 pub struct TokioFs;
 
 impl Fs for TokioFs {
@@ -25,6 +33,31 @@ impl Fs for TokioFs {
 
     async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
         let local_path = path_to_local(path).map_err(|err| Error::Path(Box::new(err)))?;
+        if options.create_new {
+            if let Some(parent_path) = local_path.parent() {
+                create_dir_all(parent_path).await?;
+            }
+            let file = tokio::fs::OpenOptions::new()
+                .read(options.read)
+                .write(true)
+                .create_new(true)
+                .open(&local_path)
+                .await
+                .map_err(|err| create_new_error(&local_path, err))?;
+
+            return Ok(TokioFile::new(file));
+        }
+        if let Some(e_tag) = options.if_match.clone() {
+            let (file, matched) =
+                spawn_blocking(move || open_if_match(&local_path, &options, &e_tag))
+                    .await
+                    .map_err(io::Error::from)??;
+
+            return Ok(TokioFile {
+                file: Some(tokio::fs::File::from_std(file)),
+                matched: Some(matched),
+            });
+        }
         if !local_path.exists() {
             if options.create {
                 if let Some(parent_path) = local_path.parent() {
@@ -71,10 +104,10 @@ impl Fs for TokioFs {
             Ok::<_, Error>(stream! {
                 for entry in entries {
                     let entry = entry?;
-                    yield Ok(FileMeta {
-                        path: Path::from_filesystem_path(entry.path()).map_err(|err| Error::Path(Box::new(err)))?,
-                        size: entry.metadata()?.len()
-                    });
+                    yield Ok(local_file_meta(
+                        Path::from_filesystem_path(entry.path()).map_err(|err| Error::Path(Box::new(err)))?,
+                        &entry.metadata()?,
+                    ));
                 }
             })
         })
@@ -82,6 +115,44 @@ impl Fs for TokioFs {
         .map_err(io::Error::from)?
     }
 
+    /// Walks the directory on the blocking thread pool, a page of entries at a time.
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        let path = path_to_local(path).map_err(|err| Error::Path(Box::new(err)))?;
+        let mut walk = spawn_blocking(move || LocalWalk::new(&path, options))
+            .await
+            .map_err(io::Error::from)??;
+
+        Ok(stream! {
+            loop {
+                let (rest, page) = spawn_blocking(move || {
+                    let page = walk.by_ref().take(LIST_PAGE).collect::<Vec<_>>();
+                    (walk, page)
+                })
+                .await
+                .map_err(io::Error::from)?;
+                let done = page.len() < LIST_PAGE;
+                for meta in page {
+                    yield meta;
+                }
+                if done {
+                    break;
+                }
+                walk = rest;
+            }
+        })
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let local_path = path_to_local(path).map_err(|err| Error::Path(Box::new(err)))?;
+        let metadata = tokio::fs::metadata(&local_path).await?;
+
+        Ok(local_file_meta(path.clone(), &metadata))
+    }
+
     async fn remove(&self, path: &Path) -> Result<(), Error> {
         let path = path_to_local(path).map_err(|err| Error::Path(Box::new(err)))?;
 
@@ -89,6 +160,14 @@ impl Fs for TokioFs {
         Ok(())
     }
 
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        let path = path_to_local(path).map_err(|err| Error::Path(Box::new(err)))?;
+
+        spawn_blocking(move || local_remove_dir_all(&path))
+            .await
+            .map_err(io::Error::from)?
+    }
+
     async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
         let from = path_to_local(from).map_err(|err| Error::Path(Box::new(err)))?;
         let to = path_to_local(to).map_err(|err| Error::Path(Box::new(err)))?;
@@ -106,4 +185,18 @@ impl Fs for TokioFs {
 
         Ok(())
     }
+
+    /// Renames the file with rename(2), which is atomic within a single file system.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let from = path_to_local(from).map_err(|err| Error::Path(Box::new(err)))?;
+        let to = path_to_local(to).map_err(|err| Error::Path(Box::new(err)))?;
+
+        tokio::fs::rename(&from, &to).await?;
+
+        Ok(())
+    }
+
+    fn atomic_rename(&self) -> bool {
+        true
+    }
 }
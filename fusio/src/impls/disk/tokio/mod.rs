@@ -8,19 +8,34 @@ use std::os::fd::{AsRawFd, FromRawFd};
 #[cfg(unix)]
 use std::os::unix::fs::FileExt;
 
+#[cfg(feature = "fs")]
+use std::time::SystemTime;
 #[cfg(not(unix))]
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+#[cfg(feature = "fs")]
+use tokio::task::spawn_blocking;
 use tokio::{fs::File, io::AsyncWriteExt, task::block_in_place};
 
+#[cfg(feature = "fs")]
+use crate::disk::local::advance_modified;
 use crate::{error::Error, IoBuf, IoBufMut, Read, Write};
 
 pub struct TokioFile {
     file: Option<File>,
+    /// The modification time of a file opened with
+    /// [`OpenOptions::if_match`](crate::fs::OpenOptions::if_match), advanced on close.
+    #[cfg(feature = "fs")]
+    matched: Option<SystemTime>,
 }
 
 impl TokioFile {
     pub(crate) fn new(file: File) -> Self {
-        Self { file: Some(file) }
+        Self {
+            file: Some(file),
+            #[cfg(feature = "fs")]
+            matched: None,
+        }
     }
 }
 
@@ -71,6 +86,13 @@ impl Write for TokioFile {
     async fn close(&mut self) -> Result<(), Error> {
         let file = self.file.as_mut().expect("close file after closed");
         File::shutdown(file).await?;
+        #[cfg(feature = "fs")]
+        if let Some(matched) = self.matched.take() {
+            let file = file.try_clone().await?.into_std().await;
+            spawn_blocking(move || advance_modified(&file, matched))
+                .await
+                .map_err(std::io::Error::from)??;
+        }
         Ok(())
     }
 }
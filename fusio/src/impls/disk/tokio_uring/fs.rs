@@ -2,12 +2,18 @@ use std::{fs, future::Future, io::ErrorKind};
 
 use async_stream::stream;
 use futures_core::Stream;
-use tokio_uring::fs::{create_dir_all, remove_file};
+use futures_util::stream;
+use tokio_uring::fs::{create_dir_all, remove_file, rename};
 
 use crate::{
-    disk::tokio_uring::TokioUringFile,
+    disk::{
+        local::{
+            create_new_error, local_file_meta, local_remove_dir_all, try_open_if_match, LocalWalk,
+        },
+        tokio_uring::TokioUringFile,
+    },
     error::Error,
-    fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
     path::{path_to_local, Path},
     MaybeSend,
 };
@@ -23,6 +29,34 @@ impl Fs for TokioUringFs {
 
     async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
         let local_path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+        if options.create_new {
+            if let Some(parent_path) = local_path.parent() {
+                create_dir_all(parent_path).await?;
+            }
+            let file = tokio_uring::fs::OpenOptions::new()
+                .read(options.read)
+                .write(true)
+                .create_new(true)
+                .open(&local_path)
+                .await
+                .map_err(|err| create_new_error(&local_path, err))?;
+
+            return Ok(TokioUringFile {
+                file: Some(file),
+                pos: 0,
+                matched: None,
+            });
+        }
+        if let Some(e_tag) = &options.if_match {
+            let (file, matched) = try_open_if_match(&local_path, &options, e_tag)?;
+            let pos = file.metadata()?.len();
+
+            return Ok(TokioUringFile {
+                file: Some(tokio_uring::fs::File::from_std(file)),
+                pos,
+                matched: Some(matched),
+            });
+        }
         if !local_path.exists() {
             if options.create {
                 if let Some(parent_path) = local_path.parent() {
@@ -50,6 +84,7 @@ impl Fs for TokioUringFs {
         Ok(TokioUringFile {
             file: Some(file),
             pos: stat.stx_size,
+            matched: None,
         })
     }
 
@@ -70,20 +105,43 @@ impl Fs for TokioUringFs {
         Ok(stream! {
             for entry in dir {
                 let entry = entry?;
-                yield Ok(FileMeta {
-                    path: Path::from_filesystem_path(entry.path()).map_err(|err| Error::Path(err.into()))?,
-                    size: entry.metadata()?.len()
-                });
+                yield Ok(local_file_meta(
+                    Path::from_filesystem_path(entry.path()).map_err(|err| Error::Path(err.into()))?,
+                    &entry.metadata()?,
+                ));
             }
         })
     }
 
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        let path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+
+        Ok(stream::iter(LocalWalk::new(&path, options)?))
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let local_path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+        let metadata = fs::metadata(local_path)?;
+
+        Ok(local_file_meta(path.clone(), &metadata))
+    }
+
     async fn remove(&self, path: &Path) -> Result<(), Error> {
         let path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
 
         Ok(remove_file(path).await?)
     }
 
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        let path = path_to_local(path).map_err(|err| Error::Path(err.into()))?;
+
+        local_remove_dir_all(&path)
+    }
+
     async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
         let from = path_to_local(from).map_err(|err| Error::Path(err.into()))?;
         let to = path_to_local(to).map_err(|err| Error::Path(err.into()))?;
@@ -101,4 +159,16 @@ impl Fs for TokioUringFs {
 
         Ok(())
     }
+
+    /// Renames the file with rename(2), which is atomic within a single file system.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let from = path_to_local(from).map_err(|err| Error::Path(err.into()))?;
+        let to = path_to_local(to).map_err(|err| Error::Path(err.into()))?;
+
+        Ok(rename(from, to).await?)
+    }
+
+    fn atomic_rename(&self) -> bool {
+        true
+    }
 }
@@ -4,8 +4,17 @@ pub mod fs;
 #[allow(unused)]
 #[cfg(feature = "fs")]
 pub use fs::TokioUringFs;
+#[cfg(feature = "fs")]
+use std::{
+    mem::ManuallyDrop,
+    os::fd::{AsRawFd, FromRawFd},
+    time::SystemTime,
+};
+
 use tokio_uring::fs::File;
 
+#[cfg(feature = "fs")]
+use crate::disk::local::advance_modified;
 use crate::{error::Error, IoBuf, IoBufMut, Read, Write};
 
 #[repr(transparent)]
@@ -44,6 +53,10 @@ where
 pub struct TokioUringFile {
     file: Option<File>,
     pos: u64,
+    /// The modification time of a file opened with
+    /// [`OpenOptions::if_match`](crate::fs::OpenOptions::if_match), advanced on close.
+    #[cfg(feature = "fs")]
+    matched: Option<SystemTime>,
 }
 
 impl From<File> for TokioUringFile {
@@ -51,6 +64,8 @@ impl From<File> for TokioUringFile {
         Self {
             file: Some(file),
             pos: 0,
+            #[cfg(feature = "fs")]
+            matched: None,
         }
     }
 }
@@ -77,7 +92,15 @@ impl Write for TokioUringFile {
     }
 
     async fn close(&mut self) -> Result<(), Error> {
-        File::close(self.file.take().expect("close file twice")).await?;
+        let file = self.file.take().expect("close file twice");
+        #[cfg(feature = "fs")]
+        if let Some(matched) = self.matched.take() {
+            // the lock is released once the file is closed, so its time is advanced before
+            let std_file =
+                ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(file.as_raw_fd()) });
+            advance_modified(&std_file, matched)?;
+        }
+        File::close(file).await?;
         Ok(())
     }
 }
@@ -0,0 +1,95 @@
+//! The sorted walk over a tree of directories shared by the disk backends for
+//! [`Fs::list_with`](crate::fs::Fs::list_with).
+
+use std::vec;
+
+use crate::{
+    fs::ListOptions,
+    path::{Path, DELIMITER},
+};
+
+/// An entry of a directory, along with the handle `H` of the backend to read it.
+pub(crate) struct DirEntry<H> {
+    /// The path of the entry, followed by the delimiter if it is a directory, so that entries
+    /// sort like the keys of an object storage.
+    key: String,
+    pub(crate) path: Path,
+    pub(crate) handle: H,
+}
+
+impl<H> DirEntry<H> {
+    pub(crate) fn new(path: Path, is_dir: bool, handle: H) -> Self {
+        let mut key = path.to_string();
+        if is_dir {
+            key.push_str(DELIMITER);
+        }
+
+        Self { key, path, handle }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.key.ends_with(DELIMITER)
+    }
+}
+
+/// What to do with the next entry of a [`SortedWalk`].
+pub(crate) enum Step<H> {
+    /// Lists the entry.
+    List(DirEntry<H>),
+    /// Reads the entries of the directory and hands them to [`SortedWalk::descend`].
+    Descend(DirEntry<H>),
+}
+
+/// The entries beneath a directory in the order of their paths, as described by [`ListOptions`].
+///
+/// The entries of a directory are sorted once the walk reaches it, so only the directories on the
+/// way to the current entry are held in memory. A directory that does not exist has no entries,
+/// like a prefix without objects on object storages.
+pub(crate) struct SortedWalk<H> {
+    options: ListOptions,
+    remaining: usize,
+    dirs: Vec<vec::IntoIter<DirEntry<H>>>,
+}
+
+impl<H> SortedWalk<H> {
+    /// Walks `entries`, the entries of the listed directory, or nothing if it does not exist.
+    pub(crate) fn new(entries: Option<Vec<DirEntry<H>>>, options: ListOptions) -> Self {
+        let mut walk = Self {
+            remaining: options.limit.unwrap_or(usize::MAX),
+            dirs: Vec::new(),
+            options,
+        };
+        if let Some(entries) = entries {
+            walk.descend(entries);
+        }
+
+        walk
+    }
+
+    /// Continues the walk with the entries of the directory of the last [`Step::Descend`].
+    pub(crate) fn descend(&mut self, mut entries: Vec<DirEntry<H>>) {
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        self.dirs.push(entries.into_iter());
+    }
+
+    /// Returns the next entry to list or to descend into, or `None` once the walk is done.
+    pub(crate) fn step(&mut self) -> Option<Step<H>> {
+        while self.remaining > 0 {
+            let Some(entry) = self.dirs.last_mut()?.next() else {
+                self.dirs.pop();
+                continue;
+            };
+            let is_dir = entry.is_dir();
+            if !self.options.lists(&entry.key, is_dir) {
+                continue;
+            }
+            if is_dir && self.options.recursive {
+                return Some(Step::Descend(entry));
+            }
+            self.remaining -= 1;
+
+            return Some(Step::List(entry));
+        }
+        None
+    }
+}
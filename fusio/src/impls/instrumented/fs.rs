@@ -0,0 +1,368 @@
+use std::sync::Arc;
+
+use futures_core::Stream;
+
+use super::{FsStats, Operation, Probe, Recorder};
+#[cfg(feature = "bytes")]
+use crate::fs::{GetResult, PutResult};
+use crate::{
+    error::Error,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::Path,
+    IoBuf, IoBufMut, MaybeSend, Read, Write,
+};
+
+/// A file system that reports every operation of `F`, and of the files it opens, to a
+/// [`Recorder`].
+///
+/// The latency of [`Fs::list`] covers the time until the stream is returned, not the time it
+/// takes to consume it.
+pub struct InstrumentedFs<F, R = FsStats> {
+    inner: F,
+    recorder: Arc<R>,
+}
+
+impl<F: Fs> InstrumentedFs<F> {
+    /// Wraps `inner` with a new [`FsStats`], available through [`InstrumentedFs::recorder`].
+    pub fn new(inner: F) -> Self {
+        Self::with_recorder(inner, Arc::new(FsStats::new()))
+    }
+}
+
+impl<F: Fs, R: Recorder> InstrumentedFs<F, R> {
+    /// Wraps `inner`, reporting to `recorder`, which may be shared with other file systems.
+    pub fn with_recorder(inner: F, recorder: Arc<R>) -> Self {
+        Self { inner, recorder }
+    }
+
+    pub fn recorder(&self) -> &Arc<R> {
+        &self.recorder
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn probe(&self, operation: Operation, path: &Path) -> Probe<'_, R> {
+        Probe::start(self.recorder.as_ref(), operation, path)
+    }
+}
+
+impl<F: Clone, R> Clone for InstrumentedFs<F, R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl<F: Fs, R: Recorder> Fs for InstrumentedFs<F, R> {
+    type File = InstrumentedFile<F::File, R>;
+
+    fn file_system(&self) -> FileSystemTag {
+        self.inner.file_system()
+    }
+
+    async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
+        let probe = self.probe(Operation::Open, path);
+        let result = probe.run(self.inner.open_options(path, options)).await;
+        probe.finish(0, result.as_ref().err());
+
+        Ok(InstrumentedFile {
+            inner: result?,
+            recorder: self.recorder.clone(),
+            path: path.clone(),
+        })
+    }
+
+    async fn create_dir_all(path: &Path) -> Result<(), Error> {
+        F::create_dir_all(path).await
+    }
+
+    async fn list(
+        &self,
+        path: &Path,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        let probe = self.probe(Operation::List, path);
+        let result = probe.run(self.inner.list(path)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        let probe = self.probe(Operation::List, path);
+        let result = probe.run(self.inner.list_with(path, options)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let probe = self.probe(Operation::Metadata, path);
+        let result = probe.run(self.inner.metadata(path)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn remove(&self, path: &Path) -> Result<(), Error> {
+        let probe = self.probe(Operation::Remove, path);
+        let result = probe.run(self.inner.remove(path)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    /// Reports the batch as a single operation, which fails if any of its files could not be
+    /// removed.
+    async fn remove_batch(&self, paths: &[Path]) -> Result<Vec<Result<(), Error>>, Error> {
+        let probe = Probe::start(
+            self.recorder.as_ref(),
+            Operation::RemoveBatch,
+            &format_args!("{} files", paths.len()),
+        );
+        let result = probe.run(self.inner.remove_batch(paths)).await;
+        let error = match &result {
+            Ok(results) => results.iter().find_map(|result| result.as_ref().err()),
+            Err(err) => Some(err),
+        };
+        probe.finish(0, error);
+
+        result
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        let probe = self.probe(Operation::RemoveDirAll, path);
+        let result = probe.run(self.inner.remove_dir_all(path)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let probe = self.probe(Operation::Copy, from);
+        let result = probe.run(self.inner.copy(from, to)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn link(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let probe = self.probe(Operation::Link, from);
+        let result = probe.run(self.inner.link(from, to)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let probe = self.probe(Operation::Rename, from);
+        let result = probe.run(self.inner.rename(from, to)).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put_stream<S>(
+        &self,
+        path: &Path,
+        stream: S,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error>
+    where
+        S: Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend,
+    {
+        let probe = self.probe(Operation::PutStream, path);
+        let result = probe
+            .run(self.inner.put_stream(path, stream, options))
+            .await;
+        let bytes = result.as_ref().map_or(0, |result| result.size);
+        probe.finish(bytes, result.as_ref().err());
+
+        result
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put(
+        &self,
+        path: &Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error> {
+        let probe = self.probe(Operation::Put, path);
+        let result = probe.run(self.inner.put(path, buf, options)).await;
+        let bytes = result.as_ref().map_or(0, |result| result.size);
+        probe.finish(bytes, result.as_ref().err());
+
+        result
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn get(&self, path: &Path) -> Result<GetResult, Error> {
+        let probe = self.probe(Operation::Get, path);
+        let result = probe.run(self.inner.get(path)).await;
+        let bytes = result
+            .as_ref()
+            .map_or(0, |result| result.bytes.len() as u64);
+        probe.finish(bytes, result.as_ref().err());
+
+        result
+    }
+
+    fn atomic_rename(&self) -> bool {
+        self.inner.atomic_rename()
+    }
+}
+
+/// A file opened by [`InstrumentedFs`], which reports its reads and writes with the number of
+/// bytes transferred.
+pub struct InstrumentedFile<F, R = FsStats> {
+    inner: F,
+    recorder: Arc<R>,
+    path: Path,
+}
+
+impl<F, R: Recorder> InstrumentedFile<F, R> {
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: Read, R: Recorder> Read for InstrumentedFile<F, R> {
+    async fn read_exact_at<B: IoBufMut>(&mut self, buf: B, pos: u64) -> (Result<(), Error>, B) {
+        let probe = Probe::start(self.recorder.as_ref(), Operation::ReadExactAt, &self.path);
+        let (result, buf) = probe.run(self.inner.read_exact_at(buf, pos)).await;
+        let bytes = if result.is_ok() {
+            buf.bytes_init() as u64
+        } else {
+            0
+        };
+        probe.finish(bytes, result.as_ref().err());
+
+        (result, buf)
+    }
+
+    async fn read_to_end_at(&mut self, buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
+        let len = buf.len();
+        let probe = Probe::start(self.recorder.as_ref(), Operation::ReadToEndAt, &self.path);
+        let (result, buf) = probe.run(self.inner.read_to_end_at(buf, pos)).await;
+        let bytes = if result.is_ok() {
+            buf.len().saturating_sub(len) as u64
+        } else {
+            0
+        };
+        probe.finish(bytes, result.as_ref().err());
+
+        (result, buf)
+    }
+
+    async fn size(&self) -> Result<u64, Error> {
+        let probe = Probe::start(self.recorder.as_ref(), Operation::Size, &self.path);
+        let result = probe.run(self.inner.size()).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+}
+
+impl<F: Write, R: Recorder> Write for InstrumentedFile<F, R> {
+    async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
+        let probe = Probe::start(self.recorder.as_ref(), Operation::WriteAll, &self.path);
+        let (result, buf) = probe.run(self.inner.write_all(buf)).await;
+        let bytes = if result.is_ok() {
+            buf.bytes_init() as u64
+        } else {
+            0
+        };
+        probe.finish(bytes, result.as_ref().err());
+
+        (result, buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        let probe = Probe::start(self.recorder.as_ref(), Operation::Flush, &self.path);
+        let result = probe.run(self.inner.flush()).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        let probe = Probe::start(self.recorder.as_ref(), Operation::Close, &self.path);
+        let result = probe.run(self.inner.close()).await;
+        probe.finish(0, result.as_ref().err());
+
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use super::InstrumentedFs;
+    use crate::{
+        error::ErrorKind,
+        fs::{Fs, OpenOptions},
+        impls::{instrumented::Operation, memory::MemoryFs},
+        path::Path,
+        Read, Write,
+    };
+
+    #[tokio::test]
+    async fn test_instrumented_fs() {
+        let fs = InstrumentedFs::new(MemoryFs::new());
+        let path = Path::parse("instrumented.file").unwrap();
+
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create(true))
+            .await
+            .unwrap();
+        file.write_all(&b"hello"[..]).await.0.unwrap();
+        file.write_all(&b" fusio"[..]).await.0.unwrap();
+        file.close().await.unwrap();
+
+        let mut file = fs.open(&path).await.unwrap();
+        let (result, _) = file.read_exact_at(vec![0u8; 5], 0).await;
+        result.unwrap();
+        let (result, _) = file.read_exact_at(vec![0u8; 5], 10).await;
+        assert!(result.is_err());
+        let (result, buf) = file.read_to_end_at(b"x".to_vec(), 6).await;
+        result.unwrap();
+        assert_eq!(buf, b"xfusio");
+
+        assert!(fs.remove(&path.child("missing")).await.is_err());
+
+        let stats = fs.recorder();
+        let open = stats.get(Operation::Open);
+        assert_eq!((open.count, open.errors), (2, 0));
+        let write = stats.get(Operation::WriteAll);
+        assert_eq!((write.count, write.bytes), (2, 11));
+        assert_eq!(write.latency.count(), 2);
+        let read = stats.get(Operation::ReadExactAt);
+        assert_eq!((read.count, read.errors, read.bytes), (2, 1, 5));
+        assert_eq!(stats.get(Operation::ReadToEndAt).bytes, 5);
+        assert_eq!(stats.get(Operation::Close).count, 1);
+        let remove = stats.get(Operation::Remove);
+        assert_eq!((remove.count, remove.errors), (1, 1));
+        assert_eq!(stats.get(Operation::Copy).count, 0);
+
+        // every instance keeps its own statistics
+        let other = InstrumentedFs::new(MemoryFs::new());
+        assert!(other.recorder().snapshot().is_empty());
+        assert_eq!(
+            other.metadata(&path).await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(other.recorder().get(Operation::Metadata).errors, 1);
+    }
+}
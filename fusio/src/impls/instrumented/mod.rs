@@ -0,0 +1,398 @@
+//! Metrics and tracing of file system operations.
+//!
+//! [`InstrumentedFs`] wraps a file system and reports every operation, including those of the
+//! files it opens, to a [`Recorder`]. The default recorder, [`FsStats`], keeps counters and
+//! latency histograms that can be queried at any time. With the `tracing` feature enabled, every
+//! operation additionally runs inside a `tracing` span.
+
+#[cfg(feature = "fs")]
+mod fs;
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[cfg(feature = "fs")]
+pub use fs::{InstrumentedFile, InstrumentedFs};
+
+use crate::{
+    error::{Error, ErrorKind},
+    MaybeSend, MaybeSync,
+};
+
+/// An operation reported by [`InstrumentedFs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    Open,
+    ReadExactAt,
+    ReadToEndAt,
+    Size,
+    WriteAll,
+    Flush,
+    Close,
+    List,
+    Metadata,
+    Remove,
+    RemoveBatch,
+    RemoveDirAll,
+    Copy,
+    Link,
+    Rename,
+    Put,
+    PutStream,
+    Get,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 18] = [
+        Operation::Open,
+        Operation::ReadExactAt,
+        Operation::ReadToEndAt,
+        Operation::Size,
+        Operation::WriteAll,
+        Operation::Flush,
+        Operation::Close,
+        Operation::List,
+        Operation::Metadata,
+        Operation::Remove,
+        Operation::RemoveBatch,
+        Operation::RemoveDirAll,
+        Operation::Copy,
+        Operation::Link,
+        Operation::Rename,
+        Operation::Put,
+        Operation::PutStream,
+        Operation::Get,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Open => "open",
+            Operation::ReadExactAt => "read_exact_at",
+            Operation::ReadToEndAt => "read_to_end_at",
+            Operation::Size => "size",
+            Operation::WriteAll => "write_all",
+            Operation::Flush => "flush",
+            Operation::Close => "close",
+            Operation::List => "list",
+            Operation::Metadata => "metadata",
+            Operation::Remove => "remove",
+            Operation::RemoveBatch => "remove_batch",
+            Operation::RemoveDirAll => "remove_dir_all",
+            Operation::Copy => "copy",
+            Operation::Link => "link",
+            Operation::Rename => "rename",
+            Operation::Put => "put",
+            Operation::PutStream => "put_stream",
+            Operation::Get => "get",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Receives the outcome of every operation performed through an [`InstrumentedFs`].
+///
+/// Implementations are called on the hot path of every operation and should be cheap, e.g. by
+/// forwarding to a metrics library.
+pub trait Recorder: MaybeSend + MaybeSync + 'static {
+    /// Records that `operation` completed after `latency`, transferring `bytes` bytes of file
+    /// content. `error` is the kind of the error if the operation failed.
+    fn record(&self, operation: Operation, latency: Duration, bytes: u64, error: Option<ErrorKind>);
+}
+
+/// A [`Recorder`] that aggregates counters and latency histograms per [`Operation`].
+#[derive(Debug, Default)]
+pub struct FsStats {
+    operations: [OperationStats; Operation::ALL.len()],
+}
+
+impl FsStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the statistics of `operation` recorded so far.
+    pub fn get(&self, operation: Operation) -> OperationSnapshot {
+        let stats = &self.operations[operation.index()];
+        OperationSnapshot {
+            count: stats.count.load(Ordering::Relaxed),
+            errors: stats.errors.load(Ordering::Relaxed),
+            bytes: stats.bytes.load(Ordering::Relaxed),
+            latency: stats.latency.snapshot(),
+        }
+    }
+
+    /// Returns the statistics of every operation that was recorded at least once.
+    pub fn snapshot(&self) -> Vec<(Operation, OperationSnapshot)> {
+        Operation::ALL
+            .into_iter()
+            .map(|operation| (operation, self.get(operation)))
+            .filter(|(_, snapshot)| snapshot.count > 0)
+            .collect()
+    }
+}
+
+impl Recorder for FsStats {
+    fn record(
+        &self,
+        operation: Operation,
+        latency: Duration,
+        bytes: u64,
+        error: Option<ErrorKind>,
+    ) {
+        let stats = &self.operations[operation.index()];
+        stats.count.fetch_add(1, Ordering::Relaxed);
+        if error.is_some() {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        stats.bytes.fetch_add(bytes, Ordering::Relaxed);
+        stats.latency.record(latency);
+    }
+}
+
+#[derive(Debug, Default)]
+struct OperationStats {
+    count: AtomicU64,
+    errors: AtomicU64,
+    bytes: AtomicU64,
+    latency: LatencyHistogram,
+}
+
+/// The statistics of a single [`Operation`], taken by [`FsStats::get`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationSnapshot {
+    /// The number of completed operations, including failed ones.
+    pub count: u64,
+    /// The number of operations that failed.
+    pub errors: u64,
+    /// The number of bytes read or written.
+    pub bytes: u64,
+    pub latency: HistogramSnapshot,
+}
+
+const BUCKETS: usize = 40;
+
+/// A histogram of latencies with exponential buckets: bucket `i` counts latencies below `2^i`
+/// microseconds, the last bucket counts everything else.
+#[derive(Debug)]
+struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKETS],
+    sum_micros: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .collect(),
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// The latencies recorded for an [`Operation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    buckets: Vec<u64>,
+    sum: Duration,
+}
+
+impl HistogramSnapshot {
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// The total latency of all recorded operations.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let count = self.count();
+        (count > 0).then(|| self.sum / count as u32)
+    }
+
+    /// Returns an upper bound of the `quantile`, e.g. `0.99` for the 99th percentile, precise up
+    /// to a factor of two.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((count as f64 * quantile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.buckets().find_map(|(upper_bound, bucket)| {
+            seen += bucket;
+            (seen >= rank).then_some(upper_bound)
+        })
+    }
+
+    /// Iterates over the exclusive upper bound of every bucket and the number of latencies in it.
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.buckets.iter().enumerate().map(|(i, count)| {
+            let upper_bound = if i == BUCKETS - 1 {
+                Duration::MAX
+            } else {
+                Duration::from_micros(1 << i)
+            };
+            (upper_bound, *count)
+        })
+    }
+}
+
+/// Measures a single operation and reports it to a [`Recorder`] once it has finished.
+pub(crate) struct Probe<'r, R> {
+    recorder: &'r R,
+    operation: Operation,
+    start: Stopwatch,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<'r, R: Recorder> Probe<'r, R> {
+    #[allow(unused_variables)]
+    pub(crate) fn start(recorder: &'r R, operation: Operation, path: &dyn fmt::Display) -> Self {
+        Self {
+            recorder,
+            operation,
+            start: Stopwatch::start(),
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "fusio",
+                operation = operation.as_str(),
+                path = %path,
+                bytes = tracing::field::Empty,
+                error = tracing::field::Empty,
+            ),
+        }
+    }
+
+    /// Runs `future` inside the span of the operation.
+    pub(crate) async fn run<T>(&self, future: impl std::future::Future<Output = T>) -> T {
+        #[cfg(feature = "tracing")]
+        let future = tracing::Instrument::instrument(future, self.span.clone());
+
+        future.await
+    }
+
+    pub(crate) fn finish(self, bytes: u64, error: Option<&Error>) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("bytes", bytes);
+            if let Some(error) = error {
+                self.span.record("error", tracing::field::display(error));
+            }
+        }
+        self.recorder.record(
+            self.operation,
+            self.start.elapsed(),
+            bytes,
+            error.map(Error::kind),
+        );
+    }
+}
+
+/// A monotonic clock that also works in browsers, where [`std::time::Instant`] panics.
+struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+    #[cfg(all(target_arch = "wasm32", any(feature = "opfs", feature = "wasm-http")))]
+    start: f64,
+}
+
+impl Stopwatch {
+    fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+            #[cfg(all(target_arch = "wasm32", any(feature = "opfs", feature = "wasm-http")))]
+            start: js_sys::Date::now(),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        cfg_if::cfg_if! {
+            if #[cfg(not(target_arch = "wasm32"))] {
+                self.start.elapsed()
+            } else if #[cfg(any(feature = "opfs", feature = "wasm-http"))] {
+                Duration::from_secs_f64((js_sys::Date::now() - self.start).max(0.0) / 1000.0)
+            } else {
+                Duration::ZERO
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{FsStats, Operation, Recorder};
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn fs_stats_aggregates_per_operation() {
+        let stats = FsStats::new();
+        stats.record(Operation::ReadExactAt, Duration::from_micros(3), 10, None);
+        stats.record(Operation::ReadExactAt, Duration::from_micros(100), 20, None);
+        stats.record(
+            Operation::ReadExactAt,
+            Duration::from_millis(5),
+            0,
+            Some(ErrorKind::Timeout),
+        );
+        stats.record(Operation::Remove, Duration::ZERO, 0, None);
+
+        let read = stats.get(Operation::ReadExactAt);
+        assert_eq!(read.count, 3);
+        assert_eq!(read.errors, 1);
+        assert_eq!(read.bytes, 30);
+        assert_eq!(read.latency.count(), 3);
+        assert_eq!(read.latency.sum(), Duration::from_micros(5103));
+        assert_eq!(read.latency.quantile(0.0), Some(Duration::from_micros(4)));
+        assert_eq!(read.latency.quantile(0.5), Some(Duration::from_micros(128)));
+        assert_eq!(
+            read.latency.quantile(1.0),
+            Some(Duration::from_micros(8192))
+        );
+
+        assert_eq!(stats.get(Operation::WriteAll).latency.quantile(0.5), None);
+        let recorded = stats
+            .snapshot()
+            .into_iter()
+            .map(|(operation, _)| operation)
+            .collect::<Vec<_>>();
+        assert_eq!(recorded, vec![Operation::ReadExactAt, Operation::Remove]);
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use futures_core::Stream;
+use futures_util::stream;
+
+use super::{lock_data, memory_e_tag, MemoryData, MemoryFile};
+use crate::{
+    error::Error,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::{Path, DELIMITER},
+};
+
+/// An in-memory file system.
+///
+/// Cloning a [`MemoryFs`] is cheap and the clone shares the same storage, so files written
+/// through one handle can be read from every other clone. Directories are implicit: every path
+/// can be written without creating its parents first.
+#[derive(Clone, Default)]
+pub struct MemoryFs {
+    files: Arc<Mutex<BTreeMap<Path, MemoryData>>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn files(&self) -> MutexGuard<'_, BTreeMap<Path, MemoryData>> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn get(&self, path: &Path) -> Result<MemoryData, Error> {
+        self.files()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+fn file_meta(path: &Path, data: &MemoryData) -> FileMeta {
+    let content = lock_data(data);
+    FileMeta {
+        path: path.clone(),
+        size: content.len() as u64,
+        e_tag: Some(memory_e_tag(&content)),
+        ..Default::default()
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::Path(Box::new(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("file not found: {}", path),
+    )))
+}
+
+impl Fs for MemoryFs {
+    type File = MemoryFile;
+
+    fn file_system(&self) -> FileSystemTag {
+        FileSystemTag::Memory
+    }
+
+    async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
+        let data = {
+            let mut files = self.files();
+            match files.get(path) {
+                Some(_) if options.create_new => {
+                    return Err(Error::PreconditionFailed {
+                        message: format!("file already exists: {}", path),
+                    })
+                }
+                Some(data) => data.clone(),
+                None if options.create => files.entry(path.clone()).or_default().clone(),
+                None => {
+                    return Err(Error::Path(Box::new(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "Path not found and option.create is false",
+                    ))))
+                }
+            }
+        };
+        if let Some(e_tag) = options.if_match {
+            // fail early, though the ETag is checked again once the writes are published
+            if memory_e_tag(&lock_data(&data)) != e_tag {
+                return Err(Error::PreconditionFailed {
+                    message: format!("ETag of {} does not match {}", path, e_tag),
+                });
+            }
+
+            return Ok(MemoryFile::if_match(
+                data,
+                options.read,
+                e_tag,
+                options.truncate,
+            ));
+        }
+        if options.truncate {
+            lock_data(&data).clear();
+        }
+
+        Ok(MemoryFile::new(data, options.read, options.write))
+    }
+
+    async fn create_dir_all(_: &Path) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Lists every file under the given prefix, including files in nested directories.
+    async fn list(
+        &self,
+        path: &Path,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        let entries = self
+            .files()
+            .iter()
+            .filter(|(file_path, _)| file_path.prefix_matches(path) && *file_path != path)
+            .map(|(file_path, data)| file_meta(file_path, data))
+            .collect::<Vec<_>>();
+
+        Ok(stream::iter(entries.into_iter().map(Ok)))
+    }
+
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>>, Error> {
+        options.check_delimiter()?;
+        let mut entries = Vec::<FileMeta>::new();
+        for (file_path, data) in self.files().iter() {
+            let Some(mut parts) = file_path.prefix_match(path) else {
+                continue;
+            };
+            let Some(child) = parts.next() else {
+                continue;
+            };
+            // the files of a directory sort next to each other, so it is listed once
+            let entry = if !options.recursive && parts.next().is_some() {
+                let dir = path.child(child);
+                if entries
+                    .last()
+                    .is_some_and(|last| last.is_dir && last.path == dir)
+                {
+                    continue;
+                }
+                if !options.lists(&format!("{dir}{DELIMITER}"), true) {
+                    continue;
+                }
+                FileMeta {
+                    path: dir,
+                    is_dir: true,
+                    ..Default::default()
+                }
+            } else {
+                if !options.lists(file_path.as_ref(), false) {
+                    continue;
+                }
+                file_meta(file_path, data)
+            };
+            if options.limit == Some(entries.len()) {
+                break;
+            }
+            entries.push(entry);
+        }
+
+        Ok(stream::iter(entries.into_iter().map(Ok)))
+    }
+
+    /// Returns the metadata of a file, or of a directory if any file exists beneath `path`.
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let files = self.files();
+        if let Some(data) = files.get(path) {
+            return Ok(file_meta(path, data));
+        }
+        if files.keys().any(|file_path| file_path.prefix_matches(path)) {
+            return Ok(FileMeta {
+                path: path.clone(),
+                is_dir: true,
+                ..Default::default()
+            });
+        }
+
+        Err(not_found(path))
+    }
+
+    async fn remove(&self, path: &Path) -> Result<(), Error> {
+        self.files()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    /// Removes every file beneath `path` under a single lock.
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        self.files()
+            .retain(|file_path, _| !file_path.prefix_matches(path) || file_path == path);
+
+        Ok(())
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let content = lock_data(&self.get(from)?).clone();
+        self.files()
+            .insert(to.clone(), Arc::new(Mutex::new(content)));
+
+        Ok(())
+    }
+
+    /// Makes `to` refer to the same content as `from`, like a hard link on local disk.
+    async fn link(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let data = self.get(from)?;
+        self.files().insert(to.clone(), data);
+
+        Ok(())
+    }
+
+    /// Moves the entry under a single lock, so the rename is atomic.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let mut files = self.files();
+        let data = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.clone(), data);
+
+        Ok(())
+    }
+
+    fn atomic_rename(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::{Error, ErrorKind},
+        fs::{Fs, OpenOptions},
+        impls::memory::MemoryFs,
+        path::Path,
+        Read, Write,
+    };
+
+    #[allow(unused)]
+    async fn test_memory_fs_read_write(fs: MemoryFs) -> Result<(), Error> {
+        let path = Path::parse("work/test.file").unwrap();
+
+        assert!(fs
+            .open_options(&path, OpenOptions::default())
+            .await
+            .is_err());
+        {
+            let mut file = fs
+                .open_options(&path, OpenOptions::default().create(true).write(true))
+                .await?;
+            #[cfg(feature = "completion-based")]
+            file.write_all(b"Hello! fusio".to_vec()).await.0?;
+            #[cfg(not(feature = "completion-based"))]
+            file.write_all(&b"Hello! fusio"[..]).await.0?;
+            file.close().await?;
+        }
+        {
+            let mut file = fs
+                .open_options(&path, OpenOptions::default().write(true))
+                .await?;
+            #[cfg(feature = "completion-based")]
+            file.write_all(b"Hello! world".to_vec()).await.0?;
+            #[cfg(not(feature = "completion-based"))]
+            file.write_all(&b"Hello! world"[..]).await.0?;
+            file.close().await?;
+        }
+        {
+            let mut file = fs.open(&path).await?;
+            assert_eq!(file.size().await?, 24);
+
+            let (result, buf) = file.read_exact_at(vec![0u8; 12], 12).await;
+            result?;
+            assert_eq!(buf.as_slice(), b"Hello! world");
+            let (result, _) = file.read_exact_at(vec![0u8; 12], 13).await;
+            assert!(result.is_err());
+        }
+        {
+            let mut file = fs
+                .open_options(&path, OpenOptions::default().truncate(true))
+                .await?;
+            let (result, buf) = file.read_to_end_at(vec![], 0).await;
+            result?;
+            assert!(buf.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[allow(unused)]
+    async fn test_memory_fs_copy_link_list(fs: MemoryFs) -> Result<(), Error> {
+        use std::pin::pin;
+
+        use futures_util::StreamExt;
+
+        let dir = Path::parse("work").unwrap();
+        let src = dir.child("src.file");
+        let copied = dir.child("copied.file");
+        let linked = dir.child("linked.file");
+
+        let mut file = fs
+            .open_options(&src, OpenOptions::default().create(true))
+            .await?;
+        #[cfg(feature = "completion-based")]
+        file.write_all(b"Hello! fusio".to_vec()).await.0?;
+        #[cfg(not(feature = "completion-based"))]
+        file.write_all(&b"Hello! fusio"[..]).await.0?;
+
+        fs.copy(&src, &copied).await?;
+        fs.link(&src, &linked).await?;
+        #[cfg(feature = "completion-based")]
+        file.write_all(b"!".to_vec()).await.0?;
+        #[cfg(not(feature = "completion-based"))]
+        file.write_all(&b"!"[..]).await.0?;
+        file.close().await?;
+
+        let (result, buf) = fs.open(&copied).await?.read_to_end_at(vec![], 0).await;
+        result?;
+        assert_eq!(buf.as_slice(), b"Hello! fusio");
+        let (result, buf) = fs.open(&linked).await?.read_to_end_at(vec![], 0).await;
+        result?;
+        assert_eq!(buf.as_slice(), b"Hello! fusio!");
+
+        let mut paths = vec![];
+        let mut stream = pin!(fs.list(&dir).await?);
+        while let Some(meta) = stream.next().await {
+            paths.push(meta?.path);
+        }
+        assert_eq!(paths, vec![copied.clone(), linked.clone(), src.clone()]);
+
+        let meta = fs.metadata(&linked).await?;
+        assert_eq!(meta.size, 13);
+        assert!(!meta.is_dir);
+        assert!(fs.metadata(&dir).await?.is_dir);
+
+        fs.remove(&src).await?;
+        assert!(fs.open(&src).await.is_err());
+        assert_eq!(
+            fs.metadata(&src).await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert!(fs.remove(&src).await.is_err());
+        // the linked file keeps the content alive
+        assert_eq!(fs.open(&linked).await?.size().await?, 13);
+
+        assert!(fs.atomic_rename());
+        fs.rename(&linked, &copied).await?;
+        assert!(fs.open(&linked).await.is_err());
+        assert_eq!(fs.open(&copied).await?.size().await?, 13);
+        assert!(fs.rename(&linked, &copied).await.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn test_tokio_memory_fs() {
+        test_memory_fs_read_write(MemoryFs::new()).await.unwrap();
+        test_memory_fs_copy_link_list(MemoryFs::new())
+            .await
+            .unwrap();
+    }
+
+    #[cfg(all(feature = "monoio", not(target_arch = "wasm32")))]
+    #[monoio::test]
+    async fn test_monoio_memory_fs() {
+        test_memory_fs_read_write(MemoryFs::new()).await.unwrap();
+        test_memory_fs_copy_link_list(MemoryFs::new())
+            .await
+            .unwrap();
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn test_memory_fs_conditional_write() {
+        let fs = MemoryFs::new();
+        let path = Path::parse("manifest").unwrap();
+
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create_new(true))
+            .await
+            .unwrap();
+        file.write_all(&b"v1"[..]).await.0.unwrap();
+        assert!(matches!(
+            fs.open_options(&path, OpenOptions::default().create_new(true))
+                .await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+
+        // the ETag is checked once the writes are published, so only the first writer to close
+        // its file wins
+        let e_tag = fs.metadata(&path).await.unwrap().e_tag.unwrap();
+        let mut first = fs
+            .open_options(&path, OpenOptions::default().if_match(e_tag.clone()))
+            .await
+            .unwrap();
+        let mut second = fs
+            .open_options(&path, OpenOptions::default().if_match(e_tag.clone()))
+            .await
+            .unwrap();
+        first.write_all(&b"v2"[..]).await.0.unwrap();
+        second.write_all(&b"v3"[..]).await.0.unwrap();
+        assert_eq!(fs.open(&path).await.unwrap().size().await.unwrap(), 2);
+        first.close().await.unwrap();
+        assert!(matches!(
+            second.close().await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+        let mut file = fs.open(&path).await.unwrap();
+        let (result, buf) = file.read_to_end_at(Vec::new(), 0).await;
+        result.unwrap();
+        assert_eq!(buf, b"v1v2");
+
+        assert!(matches!(
+            fs.open_options(&path, OpenOptions::default().truncate(true).if_match(e_tag))
+                .await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn test_memory_fs_shared_between_clones() {
+        let fs = MemoryFs::new();
+        let path = Path::parse("shared.file").unwrap();
+        {
+            let mut file = fs
+                .clone()
+                .open_options(&path, OpenOptions::default().create(true))
+                .await
+                .unwrap();
+            file.write_all(&b"shared"[..]).await.0.unwrap();
+        }
+        assert_eq!(fs.open(&path).await.unwrap().size().await.unwrap(), 6);
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn test_memory_fs_list_with() {
+        use futures_util::TryStreamExt;
+
+        use crate::fs::ListOptions;
+
+        let fs = MemoryFs::new();
+        for name in ["logs/a", "logs/b/c", "logs/b/d/e", "logs/b-x", "logs-old"] {
+            let mut file = fs
+                .open_options(
+                    &Path::parse(name).unwrap(),
+                    OpenOptions::default().create(true),
+                )
+                .await
+                .unwrap();
+            file.write_all(name.as_bytes()).await.0.unwrap();
+        }
+        let list = |options: ListOptions| {
+            let fs = fs.clone();
+            async move {
+                fs.list_with(&Path::parse("logs").unwrap(), options)
+                    .await?
+                    .map_ok(|meta| (meta.path.to_string(), meta.is_dir))
+                    .try_collect::<Vec<_>>()
+                    .await
+            }
+        };
+        let entry = |name: &str, is_dir: bool| (name.to_string(), is_dir);
+
+        assert_eq!(
+            list(ListOptions::default()).await.unwrap(),
+            [
+                entry("logs/a", false),
+                entry("logs/b-x", false),
+                entry("logs/b", true),
+            ]
+        );
+        assert_eq!(
+            list(ListOptions::default().recursive(true).limit(3))
+                .await
+                .unwrap(),
+            [
+                entry("logs/a", false),
+                entry("logs/b-x", false),
+                entry("logs/b/c", false),
+            ]
+        );
+        assert_eq!(
+            list(ListOptions::default().start_after(Path::parse("logs/b/c").unwrap()))
+                .await
+                .unwrap(),
+            [entry("logs/b", true)]
+        );
+        assert!(matches!(
+            list(ListOptions::default().delimiter("-")).await,
+            Err(Error::Unsupported { .. })
+        ));
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn test_memory_fs_remove_dir_all() {
+        let fs = MemoryFs::new();
+        for name in ["table", "table/a", "table/b/c", "table-old/d"] {
+            fs.open_options(
+                &Path::parse(name).unwrap(),
+                OpenOptions::default().create(true),
+            )
+            .await
+            .unwrap();
+        }
+
+        fs.remove_dir_all(&Path::parse("table").unwrap())
+            .await
+            .unwrap();
+        for (name, exists) in [
+            ("table", true),
+            ("table/a", false),
+            ("table/b/c", false),
+            ("table-old/d", true),
+        ] {
+            assert_eq!(
+                fs.metadata(&Path::parse(name).unwrap()).await.is_ok(),
+                exists,
+                "{name}"
+            );
+        }
+        fs.remove_dir_all(&Path::parse("missing").unwrap())
+            .await
+            .unwrap();
+    }
+}
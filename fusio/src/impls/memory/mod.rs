@@ -0,0 +1,179 @@
+//! In-memory storage backend.
+//!
+//! Files are kept in process memory and shared between every handle opened on the same
+//! [`MemoryFs`]. It is mainly intended for tests and for ephemeral data that never needs to
+//! outlive the process.
+
+#[cfg(feature = "fs")]
+pub mod fs;
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+#[cfg(feature = "fs")]
+pub use fs::MemoryFs;
+
+use crate::{error::Error, IoBuf, IoBufMut, Read, Write};
+
+pub(crate) type MemoryData = Arc<Mutex<Vec<u8>>>;
+
+pub(crate) fn lock_data(data: &MemoryData) -> MutexGuard<'_, Vec<u8>> {
+    data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Derives an ETag from the content of a file, so that equal contents share the same ETag like
+/// the content hashes used by object storages.
+pub(crate) fn memory_e_tag(content: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// The writes to a file opened with an ETag to match, which are published once the file is
+/// closed and its ETag still matches.
+struct Conditional {
+    e_tag: String,
+    truncate: bool,
+    buf: Vec<u8>,
+}
+
+/// A file stored in memory.
+///
+/// Writes are appended to the shared content and are visible to every other handle of the same
+/// file as soon as [`Write::write_all`] returns, unless the file is opened with an ETag to match.
+/// The writes of such a file are held back until [`Write::close`], which publishes them only if
+/// the ETag of the content still matches, like a conditional upload to an object storage.
+pub struct MemoryFile {
+    data: MemoryData,
+    read: bool,
+    write: bool,
+    conditional: Option<Conditional>,
+}
+
+impl MemoryFile {
+    pub(crate) fn new(data: MemoryData, read: bool, write: bool) -> Self {
+        Self {
+            data,
+            read,
+            write,
+            conditional: None,
+        }
+    }
+
+    /// Returns a file whose writes replace, or append to if `truncate` is false, the content of
+    /// `data` once it is closed, provided the ETag of the content is still `e_tag` by then.
+    pub(crate) fn if_match(data: MemoryData, read: bool, e_tag: String, truncate: bool) -> Self {
+        Self {
+            conditional: Some(Conditional {
+                e_tag,
+                truncate,
+                buf: Vec::new(),
+            }),
+            ..Self::new(data, read, true)
+        }
+    }
+}
+
+impl Default for MemoryFile {
+    fn default() -> Self {
+        Self::new(MemoryData::default(), true, true)
+    }
+}
+
+impl Write for MemoryFile {
+    async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
+        if !self.write {
+            return (
+                Err(Error::Other("file is not open as write mode".into())),
+                buf,
+            );
+        }
+        match &mut self.conditional {
+            Some(conditional) => conditional.buf.extend_from_slice(buf.as_slice()),
+            None => lock_data(&self.data).extend_from_slice(buf.as_slice()),
+        }
+
+        (Ok(()), buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        let Some(conditional) = self.conditional.take() else {
+            return Ok(());
+        };
+        let mut content = lock_data(&self.data);
+        if memory_e_tag(&content) != conditional.e_tag {
+            return Err(Error::PreconditionFailed {
+                message: format!("ETag does not match {}", conditional.e_tag),
+            });
+        }
+        if conditional.truncate {
+            *content = conditional.buf;
+        } else {
+            content.extend_from_slice(&conditional.buf);
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for MemoryFile {
+    async fn read_exact_at<B: IoBufMut>(&mut self, mut buf: B, pos: u64) -> (Result<(), Error>, B) {
+        if !self.read {
+            return (
+                Err(Error::Other("file is not open as read mode".into())),
+                buf,
+            );
+        }
+        let data = lock_data(&self.data);
+        let len = buf.bytes_init();
+        let Some(end) = (pos as usize)
+            .checked_add(len)
+            .filter(|end| *end <= data.len())
+        else {
+            return (
+                Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "read unexpected eof",
+                ))),
+                buf,
+            );
+        };
+        buf.as_slice_mut().copy_from_slice(&data[pos as usize..end]);
+
+        (Ok(()), buf)
+    }
+
+    async fn read_to_end_at(&mut self, mut buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
+        if !self.read {
+            return (
+                Err(Error::Other("file is not open as read mode".into())),
+                buf,
+            );
+        }
+        let data = lock_data(&self.data);
+        if pos as usize > data.len() {
+            return (
+                Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "read unexpected eof",
+                ))),
+                buf,
+            );
+        }
+        buf.extend_from_slice(&data[pos as usize..]);
+
+        (Ok(()), buf)
+    }
+
+    async fn size(&self) -> Result<u64, Error> {
+        Ok(lock_data(&self.data).len() as u64)
+    }
+}
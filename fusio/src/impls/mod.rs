@@ -1,8 +1,14 @@
 //! Implementations of the traits in the `fusio` crate.
 
 pub mod buffered;
+pub mod cache;
 pub mod disk;
+pub mod instrumented;
+pub mod memory;
+#[cfg(feature = "fs")]
+pub mod prefix;
 pub mod remotes;
+pub mod retry;
 
 use std::{future::Future, io::Cursor};
 
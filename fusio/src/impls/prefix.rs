@@ -0,0 +1,560 @@
+//! Scoping of a file system under a root path.
+
+use std::io;
+#[cfg(feature = "dyn")]
+use std::{pin::Pin, sync::Arc};
+
+use async_stream::stream;
+use futures_core::Stream;
+use percent_encoding::percent_decode_str;
+
+#[cfg(feature = "bytes")]
+use crate::fs::{GetResult, PutResult};
+#[cfg(feature = "dyn")]
+use crate::{
+    dynamic::{DynFile, MaybeSendFuture, MaybeSendStream},
+    DynFs,
+};
+use crate::{
+    error::Error,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::{self, Path},
+    MaybeSend,
+};
+
+/// A file system that scopes `F` under the path `root`, e.g. the prefix of a tenant in a shared
+/// bucket.
+///
+/// Every path passed to it is resolved beneath `root`, and the paths of the entries it lists or
+/// describes are reported relative to `root`. Paths with a `.` or `..` segment are rejected, so
+/// that they can not escape `root` on backends that resolve them.
+///
+/// The listings of the inner file system are started once the stream returned by [`Fs::list`] or
+/// [`Fs::list_with`] is polled, so their errors are reported by the stream.
+///
+/// [`Fs::create_dir_all`] takes no file system to find `root` in, so it fails with
+/// [`Error::Unsupported`]; use [`PrefixFs::create_dir_all`] instead, or the
+/// [`DynFs`](crate::DynFs) returned by [`PrefixFs::into_dyn`]. Generic code that calls the
+/// associated function fails as well, e.g. a [`CachedFs`](crate::cache::CachedFs) caching on a
+/// `PrefixFs` can not create its directory and so caches no blocks.
+///
+/// Its [`Fs::file_system`] is [`FileSystemTag::Prefix`], as its paths are not those of `F`.
+/// [`copy`](crate::dynamic::fs::copy) still copies within `F` between the file systems returned
+/// by [`PrefixFs::into_dyn`] that scope the same kind of file system under the same root, see
+/// [`DynFs::scope`](crate::DynFs::scope).
+#[derive(Debug, Clone)]
+pub struct PrefixFs<F> {
+    inner: F,
+    root: Path,
+}
+
+impl<F: Fs> PrefixFs<F> {
+    pub fn new(inner: F, root: Path) -> Self {
+        Self { inner, root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// Creates the directory `path` beneath the root, along with its missing parents.
+    pub async fn create_dir_all(&self, path: &Path) -> Result<(), Error> {
+        F::create_dir_all(&self.resolve(path)?).await
+    }
+
+    /// Returns this file system as a [`DynFs`] whose [`DynFs::create_dir_all`] creates the
+    /// directory beneath the root with [`PrefixFs::create_dir_all`], and whose [`DynFs::scope`]
+    /// is the kind of `F` along with the root.
+    #[cfg(feature = "dyn")]
+    pub fn into_dyn(self) -> Arc<dyn DynFs>
+    where
+        F: 'static,
+    {
+        Arc::new(DynPrefixFs(self))
+    }
+
+    /// Returns the path of `path` in the inner file system.
+    fn resolve(&self, path: &Path) -> Result<Path, Error> {
+        for part in path.parts() {
+            // `Path` encodes dot segments, which some backends decode again
+            let segment = percent_decode_str(part.as_ref()).decode_utf8_lossy();
+            if segment == "." || segment == ".." {
+                return Err(Error::Path(Box::new(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("path {} escapes the root {}", path, self.root),
+                ))));
+            }
+        }
+
+        Ok(self.root.parts().chain(path.parts()).collect())
+    }
+}
+
+/// Makes the path of `meta` relative to `root`.
+fn strip_root(root: &Path, mut meta: FileMeta) -> Result<FileMeta, Error> {
+    let path = meta
+        .path
+        .prefix_match(root)
+        .ok_or_else(|| {
+            Error::Path(Box::new(path::Error::PrefixMismatch {
+                path: meta.path.to_string(),
+                prefix: root.to_string(),
+            }))
+        })?
+        .collect();
+    meta.path = path;
+
+    Ok(meta)
+}
+
+impl<F: Fs> Fs for PrefixFs<F> {
+    type File = F::File;
+
+    fn file_system(&self) -> FileSystemTag {
+        FileSystemTag::Prefix
+    }
+
+    async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
+        self.inner.open_options(&self.resolve(path)?, options).await
+    }
+
+    async fn create_dir_all(_: &Path) -> Result<(), Error> {
+        Err(Error::Unsupported {
+            message: "PrefixFs can only create directories through PrefixFs::create_dir_all"
+                .to_string(),
+        })
+    }
+
+    async fn list(
+        &self,
+        path: &Path,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        let path = self.resolve(path)?;
+
+        Ok(stream! {
+            let entries = self.inner.list(&path).await?;
+            for await meta in entries {
+                yield strip_root(&self.root, meta?);
+            }
+        })
+    }
+
+    async fn list_with(
+        &self,
+        path: &Path,
+        mut options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        options.start_after = options
+            .start_after
+            .map(|start_after| self.resolve(&start_after))
+            .transpose()?;
+        let path = self.resolve(path)?;
+
+        Ok(stream! {
+            let entries = self.inner.list_with(&path, options).await?;
+            for await meta in entries {
+                yield strip_root(&self.root, meta?);
+            }
+        })
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        let meta = self.inner.metadata(&self.resolve(path)?).await?;
+
+        strip_root(&self.root, meta)
+    }
+
+    async fn remove(&self, path: &Path) -> Result<(), Error> {
+        self.inner.remove(&self.resolve(path)?).await
+    }
+
+    async fn remove_batch(&self, paths: &[Path]) -> Result<Vec<Result<(), Error>>, Error> {
+        let paths = paths
+            .iter()
+            .map(|path| self.resolve(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.inner.remove_batch(&paths).await
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        self.inner.remove_dir_all(&self.resolve(path)?).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.inner
+            .copy(&self.resolve(from)?, &self.resolve(to)?)
+            .await
+    }
+
+    async fn link(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.inner
+            .link(&self.resolve(from)?, &self.resolve(to)?)
+            .await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.inner
+            .rename(&self.resolve(from)?, &self.resolve(to)?)
+            .await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put_stream<S>(
+        &self,
+        path: &Path,
+        stream: S,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error>
+    where
+        S: Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend,
+    {
+        self.inner
+            .put_stream(&self.resolve(path)?, stream, options)
+            .await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put(
+        &self,
+        path: &Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error> {
+        self.inner.put(&self.resolve(path)?, buf, options).await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn get(&self, path: &Path) -> Result<GetResult, Error> {
+        let mut result = self.inner.get(&self.resolve(path)?).await?;
+        result.meta = strip_root(&self.root, result.meta)?;
+
+        Ok(result)
+    }
+
+    fn atomic_rename(&self) -> bool {
+        self.inner.atomic_rename()
+    }
+}
+
+/// The [`DynFs`] of a [`PrefixFs`], which forwards to the [`DynFs`] implemented for every
+/// [`Fs`] except for [`DynFs::create_dir_all`], as that one takes no file system, and
+/// [`DynFs::scope`].
+#[cfg(feature = "dyn")]
+struct DynPrefixFs<F>(PrefixFs<F>);
+
+#[cfg(feature = "dyn")]
+impl<F: Fs> DynFs for DynPrefixFs<F> {
+    fn file_system(&self) -> FileSystemTag {
+        Fs::file_system(&self.0)
+    }
+
+    fn open_options<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<Box<dyn DynFile>, Error>> + 's>> {
+        DynFs::open_options(&self.0, path, options)
+    }
+
+    fn create_dir_all<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        Box::pin(self.0.create_dir_all(path))
+    }
+
+    fn list<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<
+        Box<
+            dyn MaybeSendFuture<
+                    Output = Result<
+                        Pin<Box<dyn MaybeSendStream<Item = Result<FileMeta, Error>> + 's>>,
+                        Error,
+                    >,
+                > + 's,
+        >,
+    > {
+        DynFs::list(&self.0, path)
+    }
+
+    fn list_with<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        options: ListOptions,
+    ) -> Pin<
+        Box<
+            dyn MaybeSendFuture<
+                    Output = Result<
+                        Pin<Box<dyn MaybeSendStream<Item = Result<FileMeta, Error>> + 's>>,
+                        Error,
+                    >,
+                > + 's,
+        >,
+    > {
+        DynFs::list_with(&self.0, path, options)
+    }
+
+    fn metadata<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<FileMeta, Error>> + 's>> {
+        DynFs::metadata(&self.0, path)
+    }
+
+    fn remove<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        DynFs::remove(&self.0, path)
+    }
+
+    fn remove_batch<'s, 'path: 's>(
+        &'s self,
+        paths: &'path [Path],
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<Vec<Result<(), Error>>, Error>> + 's>> {
+        DynFs::remove_batch(&self.0, paths)
+    }
+
+    fn remove_dir_all<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        DynFs::remove_dir_all(&self.0, path)
+    }
+
+    fn copy<'s, 'path: 's>(
+        &'s self,
+        from: &'path Path,
+        to: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        DynFs::copy(&self.0, from, to)
+    }
+
+    fn link<'s, 'path: 's>(
+        &'s self,
+        from: &'path Path,
+        to: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        DynFs::link(&self.0, from, to)
+    }
+
+    fn rename<'s, 'path: 's>(
+        &'s self,
+        from: &'path Path,
+        to: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<(), Error>> + 's>> {
+        DynFs::rename(&self.0, from, to)
+    }
+
+    fn atomic_rename(&self) -> bool {
+        Fs::atomic_rename(&self.0)
+    }
+
+    fn scope(&self) -> Option<(FileSystemTag, &Path)> {
+        Some((self.0.inner.file_system(), &self.0.root))
+    }
+
+    #[cfg(feature = "bytes")]
+    fn put_stream<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        stream: Pin<Box<dyn MaybeSendStream<Item = Result<bytes::Bytes, Error>> + 's>>,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<PutResult, Error>> + 's>> {
+        DynFs::put_stream(&self.0, path, stream, options)
+    }
+
+    #[cfg(feature = "bytes")]
+    fn put<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<PutResult, Error>> + 's>> {
+        DynFs::put(&self.0, path, buf, options)
+    }
+
+    #[cfg(feature = "bytes")]
+    fn get<'s, 'path: 's>(
+        &'s self,
+        path: &'path Path,
+    ) -> Pin<Box<dyn MaybeSendFuture<Output = Result<GetResult, Error>> + 's>> {
+        DynFs::get(&self.0, path)
+    }
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::TryStreamExt;
+
+    use super::PrefixFs;
+    use crate::{
+        fs::{Fs, ListOptions, OpenOptions},
+        memory::MemoryFs,
+        path::Path,
+        Write,
+    };
+
+    #[tokio::test]
+    async fn test_prefix_fs_dyn() {
+        use tempfile::TempDir;
+
+        use crate::{
+            disk::TokioFs,
+            dynamic::fs::copy,
+            instrumented::{InstrumentedFs, Operation},
+        };
+
+        let memory = InstrumentedFs::new(MemoryFs::new());
+        let copies = || memory.recorder().get(Operation::Copy).count;
+        let a = PrefixFs::new(memory.clone(), Path::parse("tenants/a").unwrap()).into_dyn();
+        let b = PrefixFs::new(memory.clone(), Path::parse("tenants/b").unwrap()).into_dyn();
+        let manifest = Path::parse("manifest").unwrap();
+        let mut file = a
+            .open_options(&manifest, OpenOptions::default().create(true).write(true))
+            .await
+            .unwrap();
+        file.write_all(&b"manifest"[..]).await.0.unwrap();
+        file.close().await.unwrap();
+
+        // the roots of both tenants are in the same memory, yet the copy crosses them
+        copy(&a, &manifest, &b, &manifest).await.unwrap();
+        assert_eq!(copies(), 0);
+        // within the same root, the copy is left to the memory
+        let also_a = PrefixFs::new(memory.clone(), Path::parse("tenants/a").unwrap()).into_dyn();
+        let backup = Path::parse("manifest.bak").unwrap();
+        copy(&a, &manifest, &also_a, &backup).await.unwrap();
+        assert_eq!(copies(), 1);
+        for (root, name) in [
+            ("tenants/a", "manifest"),
+            ("tenants/b", "manifest"),
+            ("tenants/a", "manifest.bak"),
+        ] {
+            let meta = memory
+                .metadata(&Path::parse(root).unwrap().child(name))
+                .await
+                .unwrap();
+            assert_eq!(meta.size, 8);
+        }
+
+        let tmp_dir = TempDir::new().unwrap();
+        let local =
+            PrefixFs::new(TokioFs, Path::from_absolute_path(tmp_dir.path()).unwrap()).into_dyn();
+        local
+            .create_dir_all(&Path::parse("logs/0").unwrap())
+            .await
+            .unwrap();
+        assert!(tmp_dir.path().join("logs").join("0").is_dir());
+        // directories are implicit in memory
+        a.create_dir_all(&Path::parse("logs").unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prefix_fs() {
+        let memory = MemoryFs::new();
+        let fs = PrefixFs::new(memory.clone(), Path::parse("tenants/a").unwrap());
+        for name in ["logs/1", "logs/2", "manifest"] {
+            let mut file = fs
+                .open_options(
+                    &Path::parse(name).unwrap(),
+                    OpenOptions::default().create(true).write(true),
+                )
+                .await
+                .unwrap();
+            file.write_all(name.as_bytes()).await.0.unwrap();
+            file.close().await.unwrap();
+        }
+        let mut file = memory
+            .open_options(
+                &Path::parse("tenants/b/manifest").unwrap(),
+                OpenOptions::default().create(true).write(true),
+            )
+            .await
+            .unwrap();
+        file.write_all(&b"b"[..]).await.0.unwrap();
+        file.close().await.unwrap();
+
+        // the files are written beneath the root, and listed relative to it
+        assert_eq!(
+            memory
+                .metadata(&Path::parse("tenants/a/logs/1").unwrap())
+                .await
+                .unwrap()
+                .size,
+            6
+        );
+        let entries = fs
+            .list_with(
+                &Path::default(),
+                ListOptions::default()
+                    .recursive(true)
+                    .start_after(Path::parse("logs/1").unwrap()),
+            )
+            .await
+            .unwrap()
+            .map_ok(|meta| meta.path.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(entries, ["logs/2", "manifest"]);
+        assert_eq!(
+            fs.metadata(&Path::parse("manifest").unwrap())
+                .await
+                .unwrap()
+                .path,
+            Path::parse("manifest").unwrap()
+        );
+
+        fs.copy(
+            &Path::parse("manifest").unwrap(),
+            &Path::parse("manifest.bak").unwrap(),
+        )
+        .await
+        .unwrap();
+        fs.remove_dir_all(&Path::parse("logs").unwrap())
+            .await
+            .unwrap();
+        {
+            use crate::DynFs;
+
+            let fs = Arc::new(fs) as Arc<dyn DynFs>;
+            let entries = fs
+                .list(&Path::default())
+                .await
+                .unwrap()
+                .map_ok(|meta| meta.path.to_string())
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+            assert_eq!(entries, ["manifest", "manifest.bak"]);
+
+            // dot segments do not leave the root, however they are encoded
+            for path in [
+                Path::from("../b/manifest"),
+                Path::parse("%2e%2e/b/manifest").unwrap(),
+            ] {
+                let err = fs.remove(&path).await.unwrap_err();
+                assert!(err.to_string().contains("escapes the root"), "{err}");
+            }
+        }
+        assert!(memory
+            .metadata(&Path::parse("tenants/b/manifest").unwrap())
+            .await
+            .is_ok());
+    }
+}
@@ -120,6 +120,12 @@ impl<'a> AwsAuthorizer<'a> {
         self
     }
 
+    /// Signs with the given date instead of the current time.
+    pub(crate) fn with_date(mut self, date: DateTime<Utc>) -> Self {
+        self.date = Some(date);
+        self
+    }
+
     // /// Overrides the header name for security tokens, defaults to `x-amz-security-token`
     // pub(crate) fn with_token_header(mut self, header: HeaderName) -> Self {
     //     self.token_header = Some(header);
@@ -222,7 +228,10 @@ impl<'a> AwsAuthorizer<'a> {
         Ok(())
     }
 
-    #[allow(unused)]
+    /// Presigns `url` for `method` requests within `expires_in` seconds by attaching the
+    /// [AWS SigV4] query parameters
+    ///
+    /// [AWS SigV4]: https://docs.aws.amazon.com/AmazonS3/latest/API/sigv4-query-string-auth.html
     pub(crate) fn sign(&self, method: Method, url: &mut Url, expires_in: u32) {
         let date = self.date.unwrap_or_else(Utc::now);
         let scope = self.scope(date);
@@ -420,11 +429,12 @@ pub enum AuthorizeError {
     SignHashFailed(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
     #[error("Body no frame")]
     BodyNoFrame,
+    #[error("Failed to load credential: {0}")]
+    Credential(#[source] crate::error::Error),
 }
 
 /// <https://docs.aws.amazon.com/AWSEC2/latest/UserGuide/iam-roles-for-amazon-ec2.html#instance-metadata-security-credentials>
-#[allow(unused)]
-async fn instance_creds<'c, C: HttpClient>(
+pub(crate) async fn instance_creds<'c, C: HttpClient>(
     client: &'c C,
     endpoint: &'c str,
     imdsv1_fallback: bool,
@@ -512,20 +522,21 @@ async fn instance_creds<'c, C: HttpClient>(
 
     let creds: InstanceCredentials = serde_json::from_reader(response).map_err(io::Error::other)?;
 
-    let now = Utc::now();
-    let ttl = (creds.expiration - now).to_std().unwrap_or_default();
     Ok(TemporaryToken {
+        expiry: Some(creds.expiration),
         token: Arc::new(creds.into()),
     })
 }
 
+/// The credentials returned by the EC2 instance metadata and the ECS container credentials
+/// endpoints.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
-struct InstanceCredentials {
+pub(crate) struct InstanceCredentials {
     access_key_id: String,
     secret_access_key: String,
     token: String,
-    expiration: DateTime<Utc>,
+    pub(crate) expiration: DateTime<Utc>,
 }
 
 impl From<InstanceCredentials> for AwsCredential {
@@ -538,10 +549,13 @@ impl From<InstanceCredentials> for AwsCredential {
     }
 }
 
-#[allow(unused)]
-pub(crate) struct TemporaryToken<T> {
+/// A credential that is only valid until it expires.
+#[derive(Debug, Clone)]
+pub struct TemporaryToken<T> {
     /// The temporary credential
-    pub(crate) token: T,
+    pub token: T,
+    /// The time the credential expires at, or `None` if it does not expire
+    pub expiry: Option<DateTime<Utc>>,
 }
 
 #[cfg(test)]
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, fmt};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use http::request::Builder;
+use md5::{Digest, Md5};
+
+const SSE_HEADER: &str = "x-amz-server-side-encryption";
+const SSE_KMS_KEY_ID_HEADER: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+const SSE_CONTEXT_HEADER: &str = "x-amz-server-side-encryption-context";
+const SSE_CUSTOMER_PREFIX: &str = "x-amz-server-side-encryption-customer";
+const COPY_SOURCE_SSE_CUSTOMER_PREFIX: &str = "x-amz-copy-source-server-side-encryption-customer";
+
+/// How S3 encrypts the objects it stores, see
+/// <https://docs.aws.amazon.com/AmazonS3/latest/userguide/serv-side-encryption.html>.
+#[derive(Clone, PartialEq, Eq)]
+pub enum ServerSideEncryption {
+    /// Encrypts with keys managed by S3 (SSE-S3).
+    S3,
+    /// Encrypts with a key managed by AWS KMS (SSE-KMS), the AWS managed key of S3 if `key_id`
+    /// is `None`. The `context` is bound to the encrypted object and logged by AWS CloudTrail.
+    Kms {
+        key_id: Option<String>,
+        context: BTreeMap<String, String>,
+    },
+    /// Encrypts with a 256-bit key sent along with every request (SSE-C). S3 does not keep the
+    /// key, so objects can only be read with the key they were written with.
+    Customer { key: [u8; 32] },
+}
+
+impl fmt::Debug for ServerSideEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerSideEncryption::S3 => f.write_str("S3"),
+            ServerSideEncryption::Kms { key_id, context } => f
+                .debug_struct("Kms")
+                .field("key_id", key_id)
+                .field("context", context)
+                .finish(),
+            ServerSideEncryption::Customer { .. } => {
+                f.debug_struct("Customer").finish_non_exhaustive()
+            }
+        }
+    }
+}
+
+impl ServerSideEncryption {
+    /// Adds the headers of requests that create objects: `PutObject`, `CopyObject` and
+    /// `CreateMultipartUpload`.
+    pub(crate) fn apply_write(&self, builder: Builder) -> Builder {
+        match self {
+            ServerSideEncryption::S3 => builder.header(SSE_HEADER, "AES256"),
+            ServerSideEncryption::Kms { key_id, context } => {
+                let mut builder = builder.header(SSE_HEADER, "aws:kms");
+                if let Some(key_id) = key_id {
+                    builder = builder.header(SSE_KMS_KEY_ID_HEADER, key_id.as_str());
+                }
+                if !context.is_empty() {
+                    let context =
+                        serde_json::to_string(context).expect("string maps serialize to JSON");
+                    builder = builder.header(SSE_CONTEXT_HEADER, BASE64_STANDARD.encode(context));
+                }
+                builder
+            }
+            ServerSideEncryption::Customer { .. } => self.apply_customer_key(builder),
+        }
+    }
+
+    /// Adds the customer key to requests that read or extend an object encrypted with it:
+    /// `GetObject`, `HeadObject` and `UploadPart`. Other encryptions need no headers there.
+    pub(crate) fn apply_customer_key(&self, builder: Builder) -> Builder {
+        match self {
+            ServerSideEncryption::Customer { key } => {
+                customer_key(builder, SSE_CUSTOMER_PREFIX, key)
+            }
+            _ => builder,
+        }
+    }
+
+    /// Adds the customer key that decrypts the source object of a `CopyObject` request.
+    pub(crate) fn apply_copy_source(&self, builder: Builder) -> Builder {
+        match self {
+            ServerSideEncryption::Customer { key } => {
+                customer_key(builder, COPY_SOURCE_SSE_CUSTOMER_PREFIX, key)
+            }
+            _ => builder,
+        }
+    }
+}
+
+fn customer_key(builder: Builder, prefix: &str, key: &[u8; 32]) -> Builder {
+    builder
+        .header(format!("{prefix}-algorithm"), "AES256")
+        .header(format!("{prefix}-key"), BASE64_STANDARD.encode(key))
+        .header(
+            format!("{prefix}-key-MD5"),
+            BASE64_STANDARD.encode(Md5::digest(key)),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use http::Request;
+
+    use super::ServerSideEncryption;
+
+    fn headers(builder: http::request::Builder) -> Vec<(String, String)> {
+        let request = builder.body(()).unwrap();
+        request
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn encryption_headers() {
+        let write = |sse: &ServerSideEncryption| headers(sse.apply_write(Request::builder()));
+        let read = |sse: &ServerSideEncryption| headers(sse.apply_customer_key(Request::builder()));
+
+        let sse = ServerSideEncryption::S3;
+        assert_eq!(
+            write(&sse),
+            [("x-amz-server-side-encryption".into(), "AES256".into())]
+        );
+        assert!(read(&sse).is_empty());
+
+        let sse = ServerSideEncryption::Kms {
+            key_id: Some("alias/fusio".into()),
+            context: BTreeMap::from([("tenant".into(), "a".into())]),
+        };
+        assert_eq!(
+            write(&sse),
+            [
+                ("x-amz-server-side-encryption".into(), "aws:kms".into()),
+                (
+                    "x-amz-server-side-encryption-aws-kms-key-id".into(),
+                    "alias/fusio".into()
+                ),
+                (
+                    "x-amz-server-side-encryption-context".into(),
+                    // {"tenant":"a"}
+                    "eyJ0ZW5hbnQiOiJhIn0=".into()
+                ),
+            ]
+        );
+        assert!(read(&sse).is_empty());
+
+        let sse = ServerSideEncryption::Customer { key: [0; 32] };
+        let customer = [
+            (
+                "x-amz-server-side-encryption-customer-algorithm".to_string(),
+                "AES256".to_string(),
+            ),
+            (
+                "x-amz-server-side-encryption-customer-key".into(),
+                "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".into(),
+            ),
+            (
+                "x-amz-server-side-encryption-customer-key-md5".into(),
+                "cLyPS3KoaSFGi/joRB3OUQ==".into(),
+            ),
+        ];
+        assert_eq!(write(&sse), customer);
+        assert_eq!(read(&sse), customer);
+        assert_eq!(
+            headers(sse.apply_copy_source(Request::builder()))[0].0,
+            "x-amz-copy-source-server-side-encryption-customer-algorithm"
+        );
+        assert_eq!(format!("{:?}", sse), "Customer { .. }");
+    }
+}
@@ -1,6 +1,11 @@
+use http::StatusCode;
 use thiserror::Error;
 
-use crate::remotes::{aws::credential::AuthorizeError, http::HttpError};
+use super::S3ResponseError;
+use crate::{
+    error::{Error, ErrorKind},
+    remotes::{aws::credential::AuthorizeError, http::HttpError},
+};
 
 #[derive(Debug, Error)]
 pub enum S3Error {
@@ -11,3 +16,104 @@ pub enum S3Error {
     #[error("xml parse error: {0}")]
     XmlParseError(#[from] quick_xml::DeError),
 }
+
+impl S3Error {
+    /// Returns the [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            S3Error::HttpError(err) => err.kind(),
+            S3Error::AuthorizeError(_) => ErrorKind::Other,
+            S3Error::XmlParseError(_) => ErrorKind::Corrupted,
+        }
+    }
+}
+
+impl From<S3Error> for Error {
+    fn from(err: S3Error) -> Self {
+        Error::with_kind(err.kind(), err)
+    }
+}
+
+/// Maps the `Code` of an S3 error response to an [`ErrorKind`].
+///
+/// See <https://docs.aws.amazon.com/AmazonS3/latest/API/ErrorResponses.html#ErrorCodeList>
+pub(crate) fn error_code_kind(code: &str) -> Option<ErrorKind> {
+    Some(match code {
+        "NoSuchKey" | "NoSuchBucket" | "NoSuchUpload" | "NoSuchVersion" => ErrorKind::NotFound,
+        "BucketAlreadyExists" | "BucketAlreadyOwnedByYou" => ErrorKind::AlreadyExists,
+        "AccessDenied"
+        | "AllAccessDisabled"
+        | "InvalidAccessKeyId"
+        | "SignatureDoesNotMatch"
+        | "ExpiredToken"
+        | "InvalidToken" => ErrorKind::PermissionDenied,
+        "PreconditionFailed" => ErrorKind::PreconditionFailed,
+        "SlowDown"
+        | "ServiceUnavailable"
+        | "Throttling"
+        | "ThrottlingException"
+        | "RequestLimitExceeded"
+        | "TooManyRequests" => ErrorKind::Throttled,
+        "RequestTimeout" => ErrorKind::Timeout,
+        "InternalError" => ErrorKind::Unavailable,
+        "NotImplemented" => ErrorKind::Unsupported,
+        "BadDigest" | "InvalidDigest" | "IncompleteBody" | "XAmzContentSHA256Mismatch" => {
+            ErrorKind::Corrupted
+        }
+        _ => return None,
+    })
+}
+
+/// Creates the error of a non-success S3 response.
+///
+/// The kind is taken from the error code in the XML body when there is one, since S3 reuses
+/// status codes for unrelated errors, and from the status code otherwise.
+pub(crate) fn response_error(status: StatusCode, body: String) -> Error {
+    let kind = quick_xml::de::from_str::<S3ResponseError>(&body)
+        .ok()
+        .and_then(|err| error_code_kind(&err.code));
+    let err = HttpError::HttpNotSuccess { status, body };
+
+    Error::with_kind(kind.unwrap_or_else(|| err.kind()), err)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::response_error;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn response_error_kind() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Error>
+  <Code>NoSuchKey</Code>
+  <Message>The resource you requested does not exist</Message>
+  <Resource>/mybucket/myfoto.jpg</Resource>
+  <RequestId>4442587FB7D0A2F9</RequestId>
+</Error>"#;
+        assert_eq!(
+            response_error(StatusCode::NOT_FOUND, body.into()).kind(),
+            ErrorKind::NotFound
+        );
+
+        let body = r#"<Error><Code>SlowDown</Code><Message>Please reduce your request rate.</Message></Error>"#;
+        assert_eq!(
+            response_error(StatusCode::SERVICE_UNAVAILABLE, body.into()).kind(),
+            ErrorKind::Throttled
+        );
+        assert_eq!(
+            response_error(StatusCode::FORBIDDEN, String::new()).kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            response_error(StatusCode::PRECONDITION_FAILED, String::new()).kind(),
+            ErrorKind::PreconditionFailed
+        );
+        assert_eq!(
+            response_error(StatusCode::BAD_REQUEST, "not xml".into()).kind(),
+            ErrorKind::Other
+        );
+    }
+}
@@ -1,15 +1,23 @@
 pub mod credential;
+pub(crate) mod encryption;
 mod error;
 #[cfg(feature = "fs")]
 pub mod fs;
 pub(crate) mod multipart_upload;
 pub(crate) mod options;
+pub mod provider;
 pub(crate) mod s3;
 pub(crate) mod sign;
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+pub(crate) mod stand_in;
 pub(crate) mod writer;
 
-pub use credential::AwsCredential;
+pub use credential::{AwsCredential, TemporaryToken};
+pub use encryption::ServerSideEncryption;
 pub use error::S3Error;
+pub(crate) use error::{error_code_kind, response_error};
+pub use options::AddressingStyle;
+pub use provider::{CredentialChain, CredentialProvider, DynCredentialProvider};
 pub use s3::S3File;
 use serde::Deserialize;
 
@@ -20,6 +28,13 @@ const STRICT_ENCODE_SET: percent_encoding::AsciiSet = percent_encoding::NON_ALPH
     .remove(b'~');
 const STRICT_PATH_ENCODE_SET: percent_encoding::AsciiSet = STRICT_ENCODE_SET.remove(b'/');
 const CHECKSUM_HEADER: &str = "x-amz-checksum-sha256";
+const VERSION_ID_HEADER: &str = "x-amz-version-id";
+const USER_METADATA_PREFIX: &str = "x-amz-meta-";
+const STORAGE_CLASS_HEADER: &str = "x-amz-storage-class";
+const TAGGING_HEADER: &str = "x-amz-tagging";
+const TAGGING_COUNT_HEADER: &str = "x-amz-tagging-count";
+const COPY_SOURCE_HEADER: &str = "x-amz-copy-source";
+const COPY_SOURCE_RANGE_HEADER: &str = "x-amz-copy-source-range";
 
 #[derive(Default, Debug, Deserialize, PartialEq, Eq)]
 #[serde(default, rename_all = "PascalCase")]
@@ -1,23 +1,32 @@
+use std::ops::Range;
+
 use bytes::{Buf, Bytes};
 use http::{
-    header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG},
-    Method, Request, Response,
+    header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH},
+    request::Builder,
+    HeaderMap, Method, Request, Response, StatusCode,
 };
 use http_body::Body;
 use http_body_util::{BodyExt, Empty, Full};
 use itertools::Itertools;
 use percent_encoding::utf8_percent_encode;
 
-use super::fs::AmazonS3;
+use super::{encryption::ServerSideEncryption, fs::AmazonS3, options::S3Options};
 use crate::{
-    error::Error,
+    error::{Error, ErrorKind},
+    fs::{OpenOptions, WriteAttributes},
     path::Path,
     remotes::{
-        aws::{sign::Sign, S3Error, S3ResponseError, STRICT_PATH_ENCODE_SET},
+        aws::{
+            error::error_code_kind, response_error, sign::Sign, S3Error, S3ResponseError,
+            COPY_SOURCE_HEADER, COPY_SOURCE_RANGE_HEADER, STORAGE_CLASS_HEADER, STRICT_ENCODE_SET,
+            STRICT_PATH_ENCODE_SET, TAGGING_HEADER, USER_METADATA_PREFIX, VERSION_ID_HEADER,
+        },
         http::{BoxBody, HttpClient},
         serde::{
             CompleteMultipartUploadRequest, CompleteMultipartUploadRequestPart,
-            InitiateMultipartUploadResult, MultipartPart,
+            CompleteMultipartUploadResult, CopyPartResult, InitiateMultipartUploadResult,
+            MultipartPart,
         },
     },
 };
@@ -25,6 +34,42 @@ use crate::{
 pub(crate) struct MultipartUpload {
     fs: AmazonS3,
     path: Path,
+    condition: Option<WriteCondition>,
+    encryption: Option<ServerSideEncryption>,
+    attributes: WriteAttributes,
+}
+
+/// The precondition under which S3 accepts a `PutObject` or `CompleteMultipartUpload` request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WriteCondition {
+    /// Only write if the object does not exist, sent as `If-None-Match: *`.
+    NotExists,
+    /// Only write if the ETag of the object matches, sent as `If-Match: <etag>`.
+    Matches(String),
+}
+
+impl WriteCondition {
+    pub(crate) fn from_options(options: &OpenOptions) -> Option<Self> {
+        if options.create_new {
+            Some(WriteCondition::NotExists)
+        } else {
+            options.if_match.clone().map(WriteCondition::Matches)
+        }
+    }
+
+    fn apply(&self, builder: Builder) -> Builder {
+        match self {
+            WriteCondition::NotExists => builder.header(IF_NONE_MATCH, "*"),
+            WriteCondition::Matches(e_tag) => builder.header(IF_MATCH, e_tag.as_str()),
+        }
+    }
+}
+
+/// The ETag and version S3 assigned to the object an upload published.
+#[derive(Debug, Default)]
+pub(crate) struct Published {
+    pub(crate) e_tag: Option<String>,
+    pub(crate) version: Option<String>,
 }
 
 pub enum UploadType<B> {
@@ -42,23 +87,102 @@ pub enum UploadType<B> {
 
 impl MultipartUpload {
     pub fn new(fs: AmazonS3, path: Path) -> Self {
-        Self { fs, path }
+        let encryption = fs.as_ref().options.encryption.clone();
+        Self {
+            fs,
+            path,
+            condition: None,
+            encryption,
+            attributes: WriteAttributes::default(),
+        }
     }
 
-    async fn check_response(response: Response<BoxBody>) -> Result<Response<BoxBody>, Error> {
-        if !response.status().is_success() {
-            return Err(Error::Other(
-                format!(
-                    "failed to write to S3, HTTP status: {} content: {}",
-                    response.status(),
-                    String::from_utf8_lossy(
-                        &response.into_body().collect().await.unwrap().to_bytes()
+    pub(crate) fn options(&self) -> &S3Options {
+        &self.fs.as_ref().options
+    }
+
+    pub(crate) fn with_condition(mut self, condition: Option<WriteCondition>) -> Self {
+        self.condition = condition;
+        self
+    }
+
+    pub(crate) fn with_encryption(mut self, encryption: Option<ServerSideEncryption>) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    pub(crate) fn with_attributes(mut self, attributes: WriteAttributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Adds the attributes stored along with the object to requests that create it.
+    fn describe(&self, mut builder: Builder) -> Builder {
+        let attributes = &self.attributes;
+        if let Some(content_type) = &attributes.content_type {
+            builder = builder.header(CONTENT_TYPE, content_type.as_str());
+        }
+        if let Some(cache_control) = &attributes.cache_control {
+            builder = builder.header(CACHE_CONTROL, cache_control.as_str());
+        }
+        for (key, value) in &attributes.metadata {
+            builder = builder.header(format!("{USER_METADATA_PREFIX}{key}"), value.as_str());
+        }
+        if let Some(storage_class) = &attributes.storage_class {
+            builder = builder.header(STORAGE_CLASS_HEADER, storage_class.as_str());
+        }
+        if !attributes.tags.is_empty() {
+            let tags = attributes
+                .tags
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}={}",
+                        utf8_percent_encode(key, &STRICT_ENCODE_SET),
+                        utf8_percent_encode(value, &STRICT_ENCODE_SET)
                     )
-                )
-                .into(),
-            ));
+                })
+                .join("&");
+            builder = builder.header(TAGGING_HEADER, tags);
         }
-        Ok(response)
+        builder
+    }
+
+    /// Adds the encryption headers of requests that create the object.
+    fn encrypt(&self, builder: Builder) -> Builder {
+        match &self.encryption {
+            Some(encryption) => encryption.apply_write(builder),
+            None => builder,
+        }
+    }
+
+    /// Adds the customer key of requests that extend the object, if it is encrypted with one.
+    fn customer_key(&self, builder: Builder) -> Builder {
+        match &self.encryption {
+            Some(encryption) => encryption.apply_customer_key(builder),
+            None => builder,
+        }
+    }
+
+    async fn check_response(response: Response<BoxBody>) -> Result<Response<BoxBody>, Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| Error::Remote(e.into()))?
+            .to_bytes();
+        let body = String::from_utf8_lossy(&body).to_string();
+        if status == StatusCode::PRECONDITION_FAILED {
+            return Err(Error::PreconditionFailed {
+                message: format!("failed to write to S3, content: {}", body),
+            });
+        }
+
+        Err(response_error(status, body))
     }
 
     async fn send_request<B>(&self, mut request: Request<B>) -> Result<Response<BoxBody>, Error>
@@ -69,18 +193,22 @@ impl MultipartUpload {
         request
             .sign(&self.fs.as_ref().options)
             .await
-            .map_err(|e| Error::Remote(S3Error::from(e).into()))?;
+            .map_err(|e| Error::from(S3Error::from(e)))?;
         let response = self
             .fs
             .as_ref()
             .client
             .send_request(request)
             .await
-            .map_err(|e| Error::Remote(S3Error::from(e).into()))?;
+            .map_err(Error::from)?;
         Self::check_response(response).await
     }
 
-    pub(crate) async fn upload_once<B>(&self, upload_type: UploadType<B>) -> Result<(), Error>
+    /// Writes the object with a single `PutObject` or `CopyObject` request.
+    pub(crate) async fn upload_once<B>(
+        &self,
+        upload_type: UploadType<B>,
+    ) -> Result<Published, Error>
     where
         B: Body<Data = Bytes> + Clone + Unpin + Send + Sync + 'static,
         B::Error: std::error::Error + Send + Sync + 'static,
@@ -88,44 +216,48 @@ impl MultipartUpload {
         let (size, body, copy_from) = match upload_type {
             UploadType::Write { size, body } => (Some(size), body, None),
             UploadType::Copy { bucket, from, body } => {
-                let from_url = format!(
-                    "/{bucket}/{}",
-                    utf8_percent_encode(from.as_ref(), &STRICT_PATH_ENCODE_SET)
-                );
-                (None, body, Some(from_url))
+                (None, body, Some(copy_source(&bucket, &from)))
             }
         };
-        let url = format!(
-            "{}/{}",
-            self.fs.as_ref().options.endpoint,
-            utf8_percent_encode(self.path.as_ref(), &STRICT_PATH_ENCODE_SET)
-        );
-        let mut builder = Request::builder().uri(url).method(Method::PUT);
-        if let Some(from_url) = copy_from {
-            builder = builder.header("x-amz-copy-source", from_url);
+        let url = self.fs.as_ref().options.object_url(&self.path);
+        let mut builder = self.encrypt(Request::builder().uri(url).method(Method::PUT));
+        match copy_from {
+            Some(from_url) => {
+                builder = builder.header(COPY_SOURCE_HEADER, from_url);
+                if let Some(storage_class) = &self.attributes.storage_class {
+                    builder = builder.header(STORAGE_CLASS_HEADER, storage_class.as_str());
+                }
+                // the source is expected to be encrypted like the copy
+                if let Some(encryption) = &self.encryption {
+                    builder = encryption.apply_copy_source(builder);
+                }
+            }
+            None => builder = self.describe(builder),
         }
         // Tips: When the body is empty or the length is less than CONTENT_LENGTH, it may block
         if let Some(size) = size {
             builder = builder.header(CONTENT_LENGTH, size)
         }
+        if let Some(condition) = &self.condition {
+            builder = condition.apply(builder);
+        }
         let request = builder.body(body).map_err(|e| Error::Remote(e.into()))?;
-        let _ = self
-            .send_request(request)
-            .await
-            .map_err(|err| Error::Remote(err.into()))?;
+        let response = self.send_request(request).await?;
+        let headers = response.headers();
 
-        Ok(())
+        Ok(Published {
+            e_tag: header_string(headers, ETAG.as_str()),
+            version: header_string(headers, VERSION_ID_HEADER),
+        })
     }
 
     pub(crate) async fn initiate(&self) -> Result<String, Error> {
         let url = format!(
-            "{}/{}?uploads",
-            self.fs.as_ref().options.endpoint,
-            utf8_percent_encode(self.path.as_ref(), &STRICT_PATH_ENCODE_SET)
+            "{}?uploads",
+            self.fs.as_ref().options.object_url(&self.path)
         );
-        let request = Request::builder()
-            .uri(url)
-            .method(Method::POST)
+        let request = self
+            .describe(self.encrypt(Request::builder().uri(url).method(Method::POST)))
             .body(Empty::new())
             .map_err(|e| Error::Other(e.into()))?;
         let response = self.send_request(request).await?;
@@ -154,15 +286,13 @@ impl MultipartUpload {
         B::Error: std::error::Error + Send + Sync + 'static,
     {
         let url = format!(
-            "{}/{}?partNumber={}&uploadId={}",
-            self.fs.as_ref().options.endpoint,
-            utf8_percent_encode(self.path.as_ref(), &STRICT_PATH_ENCODE_SET),
+            "{}?partNumber={}&uploadId={}",
+            self.fs.as_ref().options.object_url(&self.path),
             part_num + 1,
             utf8_percent_encode(upload_id, &STRICT_PATH_ENCODE_SET),
         );
-        let request = Request::builder()
-            .uri(url)
-            .method(Method::PUT)
+        let request = self
+            .customer_key(Request::builder().uri(url).method(Method::PUT))
             .header(CONTENT_LENGTH, size)
             .body(body)
             .map_err(|e| Error::Other(e.into()))?;
@@ -180,15 +310,67 @@ impl MultipartUpload {
         })
     }
 
+    /// Copies the bytes in `range` of the object at `from` in `bucket` into a part with an
+    /// `UploadPartCopy` request.
+    pub(crate) async fn copy_part(
+        &self,
+        upload_id: &str,
+        part_num: usize,
+        bucket: &str,
+        from: &Path,
+        range: Range<u64>,
+    ) -> Result<MultipartPart, Error> {
+        let url = format!(
+            "{}?partNumber={}&uploadId={}",
+            self.fs.as_ref().options.object_url(&self.path),
+            part_num + 1,
+            utf8_percent_encode(upload_id, &STRICT_PATH_ENCODE_SET),
+        );
+        let mut builder = self
+            .customer_key(Request::builder().uri(url).method(Method::PUT))
+            .header(COPY_SOURCE_HEADER, copy_source(bucket, from))
+            .header(
+                COPY_SOURCE_RANGE_HEADER,
+                format!("bytes={}-{}", range.start, range.end - 1),
+            );
+        if let Some(encryption) = &self.encryption {
+            builder = encryption.apply_copy_source(builder);
+        }
+        let request = builder
+            .body(Empty::<Bytes>::new())
+            .map_err(|e| Error::Other(e.into()))?;
+        let response = self.send_request(request).await?;
+        // like `CompleteMultipartUpload`, a failed copy may still be answered with status 200
+        let result: CopyPartResult = quick_xml::de::from_reader(
+            response
+                .collect()
+                .await
+                .map_err(|err| Error::Remote(err.into()))?
+                .aggregate()
+                .reader(),
+        )
+        .map_err(|err| Error::Remote(Box::new(S3Error::from(err))))?;
+        if result.etag.is_empty() {
+            return Err(Error::Other(
+                format!("failed to copy part {} of {}", part_num + 1, from).into(),
+            ));
+        }
+
+        Ok(MultipartPart {
+            part_num,
+            etag: result.etag,
+        })
+    }
+
+    /// Completes the multipart upload from `parts`.
     pub(crate) async fn complete_part(
         &self,
         upload_id: &str,
         parts: &[MultipartPart],
-    ) -> Result<(), Error> {
+    ) -> Result<Published, Error> {
         let url = format!(
-            "{}/{}?uploadId={}",
-            self.fs.as_ref().options.endpoint,
-            utf8_percent_encode(self.path.as_ref(), &STRICT_PATH_ENCODE_SET),
+            "{}?uploadId={}",
+            self.fs.as_ref().options.object_url(&self.path),
             utf8_percent_encode(upload_id, &STRICT_PATH_ENCODE_SET),
         );
         let content = quick_xml::se::to_string(&CompleteMultipartUploadRequest {
@@ -202,31 +384,139 @@ impl MultipartUpload {
         })
         .map_err(|err| Error::Remote(S3Error::from(err).into()))?;
 
-        let request = Request::builder()
-            .uri(url)
-            .method(Method::POST)
+        let mut builder = self
+            .customer_key(Request::builder().uri(url).method(Method::POST))
             .header(CONTENT_LENGTH, content.len())
-            .header(CONTENT_TYPE, "application/xml")
+            .header(CONTENT_TYPE, "application/xml");
+        if let Some(condition) = &self.condition {
+            builder = condition.apply(builder);
+        }
+        let request = builder
             .body(Full::new(Bytes::from(content)))
             .map_err(|e| Error::Other(e.into()))?;
         let response = self.send_request(request).await?;
         // still check if there is any error because S3 might return error for status code 200
         // https://docs.aws.amazon.com/AmazonS3/latest/API/API_CompleteMultipartUpload.html#API_CompleteMultipartUpload_Example_4
         let (parts, body) = response.into_parts();
-        let maybe_error: S3ResponseError = quick_xml::de::from_reader(
-            body.collect()
-                .await
-                .map_err(|err| Error::Remote(err.into()))?
-                .aggregate()
-                .reader(),
-        )
-        .map_err(|err| Error::Remote(S3Error::from(err).into()))?;
+        let body = body
+            .collect()
+            .await
+            .map_err(|err| Error::Remote(err.into()))?
+            .to_bytes();
+        let maybe_error: S3ResponseError = quick_xml::de::from_reader(body.as_ref())
+            .map_err(|err| Error::Remote(S3Error::from(err).into()))?;
+        if maybe_error.code == "PreconditionFailed" {
+            return Err(Error::PreconditionFailed {
+                message: maybe_error.message,
+            });
+        }
         if !maybe_error.code.is_empty() {
-            return Err(Error::Other(
-                format!("{:#?}, {:?}", parts, maybe_error).into(),
+            return Err(Error::with_kind(
+                error_code_kind(&maybe_error.code).unwrap_or(ErrorKind::Other),
+                format!("{:#?}, {:?}", parts, maybe_error),
             ));
         }
+        let result: CompleteMultipartUploadResult = quick_xml::de::from_reader(body.as_ref())
+            .map_err(|err| Error::Remote(S3Error::from(err).into()))?;
+
+        Ok(Published {
+            e_tag: result.etag,
+            version: header_string(&parts.headers, VERSION_ID_HEADER),
+        })
+    }
+
+    /// Discards the parts uploaded so far with an `AbortMultipartUpload` request. An upload that
+    /// no longer exists counts as aborted.
+    pub(crate) async fn abort(&self, upload_id: &str) -> Result<(), Error> {
+        let url = format!(
+            "{}?uploadId={}",
+            self.fs.as_ref().options.object_url(&self.path),
+            utf8_percent_encode(upload_id, &STRICT_PATH_ENCODE_SET),
+        );
+        let request = Request::builder()
+            .uri(url)
+            .method(Method::DELETE)
+            .body(Empty::<Bytes>::new())
+            .map_err(|e| Error::Other(e.into()))?;
+
+        match self.send_request(request).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Returns the `x-amz-copy-source` of the object at `from` in `bucket`.
+fn copy_source(bucket: &str, from: &Path) -> String {
+    format!(
+        "/{bucket}/{}",
+        utf8_percent_encode(from.as_ref(), &STRICT_PATH_ENCODE_SET)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{
+        header::{IF_MATCH, IF_NONE_MATCH},
+        Request,
+    };
+
+    use super::WriteCondition;
+    use crate::fs::OpenOptions;
+
+    #[test]
+    fn write_condition_headers() {
+        assert_eq!(WriteCondition::from_options(&OpenOptions::default()), None);
+
+        let condition = WriteCondition::from_options(&OpenOptions::default().create_new(true));
+        assert_eq!(condition, Some(WriteCondition::NotExists));
+        let request = condition
+            .unwrap()
+            .apply(Request::builder())
+            .body(())
+            .unwrap();
+        assert_eq!(request.headers()[IF_NONE_MATCH], "*");
+
+        let condition = WriteCondition::from_options(&OpenOptions::default().if_match("\"etag\""));
+        assert_eq!(condition, Some(WriteCondition::Matches("\"etag\"".into())));
+        let request = condition
+            .unwrap()
+            .apply(Request::builder())
+            .body(())
+            .unwrap();
+        assert_eq!(request.headers()[IF_MATCH], "\"etag\"");
+    }
+
+    #[cfg(feature = "tokio-http")]
+    #[test]
+    fn write_attributes_headers() {
+        use super::MultipartUpload;
+        use crate::{fs::WriteAttributes, remotes::aws::fs::AmazonS3Builder};
+
+        let upload = MultipartUpload::new(AmazonS3Builder::new("data".into()).build(), "a".into())
+            .with_attributes(
+                WriteAttributes::default()
+                    .content_type("text/csv")
+                    .cache_control("no-cache")
+                    .metadata("origin", "fusio")
+                    .storage_class("INTELLIGENT_TIERING")
+                    .tag("team", "storage")
+                    .tag("cost center", "a&b"),
+            );
+        let request = upload.describe(Request::builder()).body(()).unwrap();
+        let headers = request.headers();
 
-        Ok(())
+        assert_eq!(headers["content-type"], "text/csv");
+        assert_eq!(headers["cache-control"], "no-cache");
+        assert_eq!(headers["x-amz-meta-origin"], "fusio");
+        assert_eq!(headers["x-amz-storage-class"], "INTELLIGENT_TIERING");
+        assert_eq!(headers["x-amz-tagging"], "cost%20center=a%26b&team=storage");
     }
 }
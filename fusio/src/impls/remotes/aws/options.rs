@@ -1,10 +1,57 @@
-use super::credential::AwsCredential;
+use std::sync::Arc;
 
+use percent_encoding::utf8_percent_encode;
+
+use super::{
+    encryption::ServerSideEncryption, provider::DynCredentialProvider, STRICT_PATH_ENCODE_SET,
+};
+use crate::path::Path;
+
+/// How the bucket is addressed in the URLs of requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingStyle {
+    /// The bucket is the first segment of the path, e.g. `https://s3.amazonaws.com/bucket/key`.
+    Path,
+    /// The bucket is a subdomain of the endpoint, e.g. `https://bucket.s3.amazonaws.com/key`.
+    VirtualHosted,
+}
+
+#[derive(Clone)]
 pub(crate) struct S3Options {
+    /// The URL of the bucket, without a trailing slash.
     pub(crate) endpoint: String,
     pub(crate) bucket: String,
     pub(crate) region: String,
-    pub(crate) credential: Option<AwsCredential>,
+    pub(crate) credential: Option<Arc<dyn DynCredentialProvider>>,
     pub(crate) sign_payload: bool,
     pub(crate) checksum: bool,
+    pub(crate) encryption: Option<ServerSideEncryption>,
+    /// The size of the parts of multipart uploads, before it grows for large objects.
+    pub(crate) part_size: usize,
+    pub(crate) max_in_flight_parts: usize,
+    /// The size of the ranges large reads are split into.
+    pub(crate) read_part_size: usize,
+    pub(crate) max_in_flight_reads: usize,
+}
+
+impl S3Options {
+    /// Returns the URL of the object at `path`.
+    pub(crate) fn object_url(&self, path: &Path) -> String {
+        format!(
+            "{}/{}",
+            self.endpoint,
+            utf8_percent_encode(path.as_ref(), &STRICT_PATH_ENCODE_SET)
+        )
+    }
+
+    /// Returns the URL of `bucket` when addressed like the bucket of these options.
+    pub(crate) fn bucket_url(&self, bucket: &str) -> String {
+        match self.endpoint.strip_suffix(&format!("/{}", self.bucket)) {
+            Some(endpoint) => format!("{endpoint}/{bucket}"),
+            None => {
+                self.endpoint
+                    .replacen(&format!("://{}.", self.bucket), &format!("://{bucket}."), 1)
+            }
+        }
+    }
 }
@@ -0,0 +1,836 @@
+//! Sources of [`AwsCredential`]s.
+//!
+//! A [`CredentialProvider`] fetches the credential requests to S3 are signed with. Besides static
+//! credentials, i.e. an [`AwsCredential`] itself, providers exist for the shared credentials and
+//! config files, web identity tokens, ECS container credentials and the EC2 instance metadata
+//! service. [`CredentialChain::from_env`] combines them in the order of the AWS SDKs, and
+//! [`RefreshingProvider`] caches temporary credentials until shortly before they expire.
+
+use std::{
+    collections::HashMap,
+    env,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes};
+use chrono::{DateTime, TimeDelta, Utc};
+use fusio_core::{MaybeSend, MaybeSendFuture, MaybeSync};
+use http::{header::AUTHORIZATION, Method, Request};
+use http_body_util::{BodyExt, Empty};
+use serde::Deserialize;
+use url::Url;
+
+use super::{
+    credential::{instance_creds, AwsCredential, InstanceCredentials, TemporaryToken},
+    S3Error,
+};
+use crate::{
+    error::{Error, ErrorKind},
+    remotes::http::{default_client, DynHttpClient, HttpClient, HttpError},
+};
+
+const DEFAULT_INSTANCE_METADATA_ENDPOINT: &str = "http://169.254.169.254";
+const DEFAULT_CONTAINER_ENDPOINT: &str = "http://169.254.170.2";
+const DEFAULT_SESSION_NAME: &str = "fusio";
+const DEFAULT_REFRESH_BEFORE: Duration = Duration::from_secs(5 * 60);
+
+/// Provides the [`AwsCredential`] requests are signed with.
+pub trait CredentialProvider: MaybeSend + MaybeSync {
+    /// Fetches a credential, along with the time it expires at if it is temporary.
+    fn credential(
+        &self,
+    ) -> impl Future<Output = Result<TemporaryToken<Arc<AwsCredential>>, Error>> + MaybeSend;
+}
+
+/// A dyn compatible version of [`CredentialProvider`].
+pub trait DynCredentialProvider: MaybeSend + MaybeSync {
+    fn dyn_credential(
+        &self,
+    ) -> Pin<
+        Box<dyn MaybeSendFuture<Output = Result<TemporaryToken<Arc<AwsCredential>>, Error>> + '_>,
+    >;
+}
+
+impl<P: CredentialProvider> DynCredentialProvider for P {
+    fn dyn_credential(
+        &self,
+    ) -> Pin<
+        Box<dyn MaybeSendFuture<Output = Result<TemporaryToken<Arc<AwsCredential>>, Error>> + '_>,
+    > {
+        Box::pin(self.credential())
+    }
+}
+
+/// A static credential, which never expires.
+impl CredentialProvider for AwsCredential {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        Ok(TemporaryToken {
+            token: Arc::new(self.clone()),
+            expiry: None,
+        })
+    }
+}
+
+/// Caches the credential of `P` and fetches a new one once the cached one is about to expire.
+pub struct RefreshingProvider<P> {
+    inner: P,
+    refresh_before: TimeDelta,
+    cache: Mutex<Option<TemporaryToken<Arc<AwsCredential>>>>,
+}
+
+impl<P: CredentialProvider> RefreshingProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            refresh_before: TimeDelta::from_std(DEFAULT_REFRESH_BEFORE).unwrap(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Sets how long before its expiry a credential is replaced, five minutes by default.
+    pub fn refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = TimeDelta::from_std(refresh_before).unwrap_or(TimeDelta::MAX);
+        self
+    }
+
+    fn cache(&self) -> MutexGuard<'_, Option<TemporaryToken<Arc<AwsCredential>>>> {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<P: CredentialProvider> CredentialProvider for RefreshingProvider<P> {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        let cached = self.cache().clone().filter(|token| {
+            token.expiry.is_none_or(|expiry| {
+                expiry
+                    .checked_sub_signed(self.refresh_before)
+                    .is_some_and(|refresh_at| refresh_at > Utc::now())
+            })
+        });
+        if let Some(token) = cached {
+            return Ok(token);
+        }
+        let token = self.inner.credential().await?;
+        *self.cache() = Some(token.clone());
+
+        Ok(token)
+    }
+}
+
+/// Tries a list of providers in order and returns the credential of the first one that succeeds.
+#[derive(Default)]
+pub struct CredentialChain {
+    providers: Vec<Box<dyn DynCredentialProvider>>,
+}
+
+impl CredentialChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_provider(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// The default chain of the AWS SDKs, configured from the environment:
+    ///
+    /// 1. `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+    /// 2. the profile `AWS_PROFILE` of the shared credentials and config files, see
+    ///    [`ProfileProvider`]
+    /// 3. the web identity token file `AWS_WEB_IDENTITY_TOKEN_FILE`, see [`WebIdentityProvider`]
+    /// 4. ECS container credentials, see [`ContainerProvider`]
+    /// 5. the EC2 instance metadata service, unless `AWS_EC2_METADATA_DISABLED` is `true`, see
+    ///    [`InstanceMetadataProvider`]
+    pub fn from_env() -> Self {
+        Self::from_env_with_client(Arc::from(default_client()))
+    }
+
+    /// Like [`CredentialChain::from_env`], but sends the requests of the providers through
+    /// `client`.
+    pub fn from_env_with_client(client: Arc<dyn DynHttpClient>) -> Self {
+        Self::from_lookup(client, |name| env::var(name).ok())
+    }
+
+    fn from_lookup(client: Arc<dyn DynHttpClient>, var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut chain = Self::new();
+
+        if let (Some(key_id), Some(secret_key)) =
+            (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY"))
+        {
+            chain = chain.with_provider(AwsCredential {
+                key_id,
+                secret_key,
+                token: var("AWS_SESSION_TOKEN"),
+            });
+        }
+
+        let home = var("HOME")
+            .or_else(|| var("USERPROFILE"))
+            .map(PathBuf::from);
+        let mut profile =
+            ProfileProvider::new(var("AWS_PROFILE").unwrap_or_else(|| "default".into()));
+        profile.credentials_file = var("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| {
+                home.as_ref()
+                    .map(|home| home.join(".aws").join("credentials"))
+            });
+        profile.config_file = var("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".aws").join("config")));
+        chain = chain.with_provider(profile);
+
+        if let (Some(token_file), Some(role_arn)) =
+            (var("AWS_WEB_IDENTITY_TOKEN_FILE"), var("AWS_ROLE_ARN"))
+        {
+            let endpoint = var("AWS_ENDPOINT_URL_STS").unwrap_or_else(|| {
+                let region = var("AWS_REGION")
+                    .or_else(|| var("AWS_DEFAULT_REGION"))
+                    .unwrap_or_else(|| "us-east-1".into());
+                format!("https://sts.{region}.amazonaws.com")
+            });
+            let mut provider =
+                WebIdentityProvider::new(client.clone(), token_file, role_arn, endpoint);
+            if let Some(session_name) = var("AWS_ROLE_SESSION_NAME") {
+                provider = provider.session_name(session_name);
+            }
+            chain = chain.with_provider(provider);
+        }
+
+        let container_uri = var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI")
+            .map(|uri| format!("{DEFAULT_CONTAINER_ENDPOINT}{uri}"))
+            .or_else(|| var("AWS_CONTAINER_CREDENTIALS_FULL_URI"));
+        if let Some(uri) = container_uri {
+            let mut provider = ContainerProvider::new(client.clone(), uri);
+            if let Some(token_file) = var("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE") {
+                provider = provider.auth_token_file(token_file);
+            } else if let Some(token) = var("AWS_CONTAINER_AUTHORIZATION_TOKEN") {
+                provider = provider.auth_token(token);
+            }
+            chain = chain.with_provider(provider);
+        }
+
+        let imds_disabled = var("AWS_EC2_METADATA_DISABLED")
+            .is_some_and(|disabled| disabled.eq_ignore_ascii_case("true"));
+        if !imds_disabled {
+            let endpoint = var("AWS_EC2_METADATA_SERVICE_ENDPOINT")
+                .unwrap_or_else(|| DEFAULT_INSTANCE_METADATA_ENDPOINT.into());
+            chain = chain.with_provider(InstanceMetadataProvider::new(client, endpoint));
+        }
+
+        chain
+    }
+}
+
+impl CredentialProvider for CredentialChain {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        let mut errors = Vec::with_capacity(self.providers.len());
+        for provider in &self.providers {
+            match provider.dyn_credential().await {
+                Ok(token) => return Ok(token),
+                Err(err) => errors.push(err.to_string()),
+            }
+        }
+
+        Err(Error::with_kind(
+            ErrorKind::PermissionDenied,
+            format!("no AWS credential found: [{}]", errors.join("; ")),
+        ))
+    }
+}
+
+/// Reads a profile of the shared credentials file, usually `~/.aws/credentials`, or of the
+/// shared config file, usually `~/.aws/config`.
+///
+/// Only static keys are supported; profiles that assume a role are not.
+pub struct ProfileProvider {
+    profile: String,
+    credentials_file: Option<PathBuf>,
+    config_file: Option<PathBuf>,
+}
+
+impl ProfileProvider {
+    pub fn new(profile: impl Into<String>) -> Self {
+        Self {
+            profile: profile.into(),
+            credentials_file: None,
+            config_file: None,
+        }
+    }
+
+    pub fn credentials_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.credentials_file = Some(path.into());
+        self
+    }
+
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+}
+
+impl CredentialProvider for ProfileProvider {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        // profiles of the config file are prefixed, except for the default profile
+        let config_section = if self.profile == "default" {
+            self.profile.clone()
+        } else {
+            format!("profile {}", self.profile)
+        };
+        let files = [
+            (&self.credentials_file, &self.profile),
+            (&self.config_file, &config_section),
+        ];
+        for (file, section) in files {
+            let Some(file) = file else {
+                continue;
+            };
+            let content = match read_file(file).await {
+                Ok(content) => content,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let Some(properties) = parse_ini(&content).remove(section.as_str()) else {
+                continue;
+            };
+            if let (Some(key_id), Some(secret_key)) = (
+                properties.get("aws_access_key_id"),
+                properties.get("aws_secret_access_key"),
+            ) {
+                return Ok(TemporaryToken {
+                    token: Arc::new(AwsCredential {
+                        key_id: key_id.clone(),
+                        secret_key: secret_key.clone(),
+                        token: properties.get("aws_session_token").cloned(),
+                    }),
+                    expiry: None,
+                });
+            }
+        }
+
+        Err(Error::with_kind(
+            ErrorKind::NotFound,
+            format!("no credential for profile {}", self.profile),
+        ))
+    }
+}
+
+/// Parses the sections of an INI file into their properties.
+fn parse_ini(content: &str) -> HashMap<&str, HashMap<String, String>> {
+    let mut sections = HashMap::<&str, HashMap<String, String>>::new();
+    let mut section = None;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = Some(name.trim());
+            continue;
+        }
+        if let (Some(section), Some((key, value))) = (section, line.split_once('=')) {
+            sections
+                .entry(section)
+                .or_default()
+                .insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    sections
+}
+
+/// Exchanges a web identity token, e.g. of an EKS service account, for temporary credentials
+/// with the STS `AssumeRoleWithWebIdentity` action.
+pub struct WebIdentityProvider {
+    client: Arc<dyn DynHttpClient>,
+    token_file: PathBuf,
+    role_arn: String,
+    session_name: String,
+    endpoint: String,
+}
+
+impl WebIdentityProvider {
+    pub fn new(
+        client: Arc<dyn DynHttpClient>,
+        token_file: impl Into<PathBuf>,
+        role_arn: impl Into<String>,
+        endpoint: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            token_file: token_file.into(),
+            role_arn: role_arn.into(),
+            session_name: DEFAULT_SESSION_NAME.into(),
+            endpoint: endpoint.into(),
+        }
+    }
+
+    pub fn session_name(mut self, session_name: impl Into<String>) -> Self {
+        self.session_name = session_name.into();
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleWithWebIdentityResponse {
+    assume_role_with_web_identity_result: AssumeRoleWithWebIdentityResult,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleWithWebIdentityResult {
+    credentials: StsCredentials,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expiration: DateTime<Utc>,
+}
+
+impl From<StsCredentials> for AwsCredential {
+    fn from(credentials: StsCredentials) -> Self {
+        Self {
+            key_id: credentials.access_key_id,
+            secret_key: credentials.secret_access_key,
+            token: Some(credentials.session_token),
+        }
+    }
+}
+
+impl CredentialProvider for WebIdentityProvider {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        let token = read_file(&self.token_file).await?;
+        let mut url = Url::parse(&self.endpoint).map_err(HttpError::from)?;
+        url.query_pairs_mut()
+            .append_pair("Action", "AssumeRoleWithWebIdentity")
+            .append_pair("DurationSeconds", "3600")
+            .append_pair("RoleArn", &self.role_arn)
+            .append_pair("RoleSessionName", &self.session_name)
+            .append_pair("Version", "2011-06-15")
+            .append_pair("WebIdentityToken", token.trim());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(url.as_str())
+            .body(Empty::<Bytes>::new())
+            .map_err(HttpError::from)?;
+
+        let body = send(&self.client, request).await?;
+        let response: AssumeRoleWithWebIdentityResponse =
+            quick_xml::de::from_reader(body.reader()).map_err(S3Error::from)?;
+        let credentials = response.assume_role_with_web_identity_result.credentials;
+
+        Ok(TemporaryToken {
+            expiry: Some(credentials.expiration),
+            token: Arc::new(credentials.into()),
+        })
+    }
+}
+
+/// Fetches the credentials of an ECS task from the container credentials endpoint.
+pub struct ContainerProvider {
+    client: Arc<dyn DynHttpClient>,
+    uri: String,
+    auth_token: Option<String>,
+    auth_token_file: Option<PathBuf>,
+}
+
+impl ContainerProvider {
+    pub fn new(client: Arc<dyn DynHttpClient>, uri: impl Into<String>) -> Self {
+        Self {
+            client,
+            uri: uri.into(),
+            auth_token: None,
+            auth_token_file: None,
+        }
+    }
+
+    /// Sets the token sent in the `Authorization` header.
+    pub fn auth_token(mut self, auth_token: impl Into<String>) -> Self {
+        self.auth_token = Some(auth_token.into());
+        self
+    }
+
+    /// Sets a file to read the token sent in the `Authorization` header from on every request,
+    /// which takes precedence over [`ContainerProvider::auth_token`].
+    pub fn auth_token_file(mut self, auth_token_file: impl Into<PathBuf>) -> Self {
+        self.auth_token_file = Some(auth_token_file.into());
+        self
+    }
+}
+
+impl CredentialProvider for ContainerProvider {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        let auth_token = match &self.auth_token_file {
+            Some(file) => Some(read_file(file).await?.trim().to_string()),
+            None => self.auth_token.clone(),
+        };
+        let mut request = Request::builder().method(Method::GET).uri(&self.uri);
+        if let Some(auth_token) = auth_token {
+            request = request.header(AUTHORIZATION, auth_token);
+        }
+        let request = request
+            .body(Empty::<Bytes>::new())
+            .map_err(HttpError::from)?;
+
+        let body = send(&self.client, request).await?;
+        let credentials: InstanceCredentials = serde_json::from_slice(&body)
+            .map_err(|err| Error::with_kind(ErrorKind::Corrupted, err))?;
+
+        Ok(TemporaryToken {
+            expiry: Some(credentials.expiration),
+            token: Arc::new(credentials.into()),
+        })
+    }
+}
+
+/// Fetches the credentials of the role of an EC2 instance from the instance metadata service,
+/// using IMDSv2 sessions.
+pub struct InstanceMetadataProvider {
+    client: Arc<dyn DynHttpClient>,
+    endpoint: String,
+    imdsv1_fallback: bool,
+}
+
+impl InstanceMetadataProvider {
+    pub fn new(client: Arc<dyn DynHttpClient>, endpoint: impl Into<String>) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.into(),
+            imdsv1_fallback: false,
+        }
+    }
+
+    /// Whether to fall back to IMDSv1 if the service refuses to create an IMDSv2 session.
+    pub fn imdsv1_fallback(mut self, imdsv1_fallback: bool) -> Self {
+        self.imdsv1_fallback = imdsv1_fallback;
+        self
+    }
+}
+
+impl CredentialProvider for InstanceMetadataProvider {
+    async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+        instance_creds(&self.client, &self.endpoint, self.imdsv1_fallback)
+            .await
+            .map_err(Error::Other)
+    }
+}
+
+/// Reads the file at `path` without blocking the runtime. Providers read their files on every
+/// call, so that rotated tokens are picked up.
+async fn read_file(path: &Path) -> io::Result<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "monoio", unix))] {
+            String::from_utf8(monoio::fs::read(path).await?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        } else if #[cfg(any(
+            all(feature = "tokio", not(feature = "completion-based")),
+            all(feature = "tokio-uring", target_os = "linux"),
+            feature = "tokio-http"
+        ))] {
+            tokio::fs::read_to_string(path).await
+        } else {
+            std::fs::read_to_string(path)
+        }
+    }
+}
+
+/// Sends `request` and returns the body of the response if it succeeded.
+async fn send(
+    client: &Arc<dyn DynHttpClient>,
+    request: Request<Empty<Bytes>>,
+) -> Result<Bytes, Error> {
+    let response = client.send_request(request).await?;
+    let status = response.status();
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(|err| Error::Remote(Box::new(err)))?
+        .to_bytes();
+    if !status.is_success() {
+        return Err(HttpError::HttpNotSuccess {
+            status,
+            body: String::from_utf8_lossy(&body).to_string(),
+        }
+        .into());
+    }
+
+    Ok(body)
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::Write,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use chrono::{DateTime, TimeDelta, Utc};
+    use http::{Method, Response, StatusCode};
+    use tempfile::NamedTempFile;
+
+    use super::{
+        CredentialChain, CredentialProvider, ProfileProvider, RefreshingProvider, TemporaryToken,
+    };
+    use crate::{
+        error::{Error, ErrorKind},
+        remotes::aws::{stand_in::StandIn, AwsCredential},
+    };
+
+    /// Answers requests with canned responses by path, in place of the AWS endpoints.
+    fn endpoints(responses: &[(&'static str, String)]) -> Arc<StandIn> {
+        let responses = responses.iter().cloned().collect::<HashMap<_, _>>();
+
+        Arc::new(StandIn::new(move |_, target| {
+            let path = target.split('?').next().unwrap_or_default();
+            match responses.get(path) {
+                Some(body) => Response::new(body.clone()),
+                None => Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(String::new())
+                    .unwrap(),
+            }
+        }))
+    }
+
+    fn env_chain(stand_in: &Arc<StandIn>, vars: &[(&str, &str)]) -> CredentialChain {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        CredentialChain::from_lookup(stand_in.clone(), |name| vars.get(name).cloned())
+    }
+
+    fn temp_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    const EXPIRATION: &str = "2030-01-01T00:00:00Z";
+
+    fn instance_credentials(key_id: &str) -> String {
+        format!(
+            r#"{{"Code":"Success","AccessKeyId":"{key_id}","SecretAccessKey":"secret","Token":"token","Expiration":"{EXPIRATION}"}}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_environment_credential() {
+        let stand_in = endpoints(&[]);
+        let chain = env_chain(
+            &stand_in,
+            &[
+                ("AWS_ACCESS_KEY_ID", "env"),
+                ("AWS_SECRET_ACCESS_KEY", "secret"),
+                ("AWS_SESSION_TOKEN", "token"),
+            ],
+        );
+
+        let credential = chain.credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "env");
+        assert_eq!(credential.token.token.as_deref(), Some("token"));
+        assert_eq!(credential.expiry, None);
+        assert!(stand_in.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_profile_provider() {
+        let credentials = temp_file(
+            "[default]\naws_access_key_id = default\naws_secret_access_key = secret\n\n\
+             # comment\n[dev]\naws_access_key_id=dev\naws_secret_access_key=secret\n",
+        );
+        let config = temp_file(
+            "[profile ops]\nregion = eu-west-1\naws_access_key_id = ops\n\
+             aws_secret_access_key = secret\naws_session_token = token\n",
+        );
+        let provider = |profile| {
+            ProfileProvider::new(profile)
+                .credentials_file(credentials.path())
+                .config_file(config.path())
+        };
+
+        let credential = provider("dev").credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "dev");
+        let credential = provider("ops").credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "ops");
+        assert_eq!(credential.token.token.as_deref(), Some("token"));
+
+        let stand_in = endpoints(&[]);
+        let chain = env_chain(
+            &stand_in,
+            &[
+                (
+                    "AWS_SHARED_CREDENTIALS_FILE",
+                    credentials.path().to_str().unwrap(),
+                ),
+                ("AWS_EC2_METADATA_DISABLED", "true"),
+            ],
+        );
+        assert_eq!(chain.credential().await.unwrap().token.key_id, "default");
+
+        let err = provider("missing").credential().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_web_identity_provider() {
+        let token_file = temp_file("web-identity-token\n");
+        let stand_in = endpoints(&[(
+            "/",
+            format!(
+                r#"<AssumeRoleWithWebIdentityResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleWithWebIdentityResult>
+    <Credentials>
+      <AccessKeyId>sts</AccessKeyId>
+      <SecretAccessKey>secret</SecretAccessKey>
+      <SessionToken>token</SessionToken>
+      <Expiration>{EXPIRATION}</Expiration>
+    </Credentials>
+  </AssumeRoleWithWebIdentityResult>
+</AssumeRoleWithWebIdentityResponse>"#
+            ),
+        )]);
+        let chain = env_chain(
+            &stand_in,
+            &[
+                (
+                    "AWS_WEB_IDENTITY_TOKEN_FILE",
+                    token_file.path().to_str().unwrap(),
+                ),
+                ("AWS_ROLE_ARN", "arn:aws:iam::123456789012:role/fusio"),
+                ("AWS_ENDPOINT_URL_STS", "http://sts.local"),
+                ("AWS_EC2_METADATA_DISABLED", "true"),
+            ],
+        );
+
+        let credential = chain.credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "sts");
+        assert_eq!(credential.token.token.as_deref(), Some("token"));
+        assert_eq!(
+            credential.expiry,
+            Some(EXPIRATION.parse::<DateTime<Utc>>().unwrap())
+        );
+
+        let requests = stand_in.requests();
+        assert_eq!(requests.len(), 1);
+        let query = &requests[0].1;
+        assert!(query.contains("Action=AssumeRoleWithWebIdentity"));
+        assert!(query.contains("RoleSessionName=fusio"));
+        assert!(query.ends_with("WebIdentityToken=web-identity-token"));
+    }
+
+    #[tokio::test]
+    async fn test_container_provider() {
+        let stand_in = endpoints(&[("/creds", instance_credentials("ecs"))]);
+        let chain = env_chain(
+            &stand_in,
+            &[
+                (
+                    "AWS_CONTAINER_CREDENTIALS_FULL_URI",
+                    "http://ecs.local/creds",
+                ),
+                ("AWS_CONTAINER_AUTHORIZATION_TOKEN", "auth"),
+                ("AWS_EC2_METADATA_DISABLED", "true"),
+            ],
+        );
+
+        let credential = chain.credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "ecs");
+        assert_eq!(
+            stand_in.requests_with_headers()[0].2["authorization"],
+            "auth"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_instance_metadata_provider() {
+        let stand_in = endpoints(&[
+            ("/latest/api/token", "imds-token".into()),
+            ("/latest/meta-data/iam/security-credentials/", "role".into()),
+            (
+                "/latest/meta-data/iam/security-credentials/role",
+                instance_credentials("ec2"),
+            ),
+        ]);
+        let chain = env_chain(
+            &stand_in,
+            &[("AWS_EC2_METADATA_SERVICE_ENDPOINT", "http://imds.local")],
+        );
+
+        let credential = chain.credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "ec2");
+        let requests = stand_in.requests_with_headers();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].0, Method::PUT);
+        assert_eq!(requests[2].2["x-aws-ec2-metadata-token"], "imds-token");
+
+        let stand_in = endpoints(&[]);
+        let err = env_chain(&stand_in, &[]).credential().await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    /// Returns credentials that expire after `ttl`, counting how often they were fetched.
+    struct Expiring {
+        ttl: TimeDelta,
+        fetches: AtomicUsize,
+    }
+
+    impl CredentialProvider for Expiring {
+        async fn credential(&self) -> Result<TemporaryToken<Arc<AwsCredential>>, Error> {
+            let fetches = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+
+            Ok(TemporaryToken {
+                token: Arc::new(AwsCredential {
+                    key_id: fetches.to_string(),
+                    secret_key: "secret".into(),
+                    token: None,
+                }),
+                expiry: Some(Utc::now() + self.ttl),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refreshing_provider() {
+        let provider = |ttl| {
+            RefreshingProvider::new(Expiring {
+                ttl,
+                fetches: AtomicUsize::new(0),
+            })
+            .refresh_before(Duration::from_secs(60))
+        };
+
+        let provider_hour = provider(TimeDelta::hours(1));
+        provider_hour.credential().await.unwrap();
+        let credential = provider_hour.credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "1");
+
+        // a credential that expires within a minute is refreshed on every use
+        let provider_seconds = provider(TimeDelta::seconds(30));
+        provider_seconds.credential().await.unwrap();
+        let credential = provider_seconds.credential().await.unwrap();
+        assert_eq!(credential.token.key_id, "2");
+    }
+}
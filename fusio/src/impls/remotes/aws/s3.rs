@@ -1,186 +1,365 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    io,
+    ops::Range,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::SystemTime,
+};
 
-use bytes::Buf;
+use bytes::{Buf, Bytes};
+use chrono::DateTime;
+use futures_core::Stream;
+use futures_util::{
+    stream::{self, FuturesUnordered},
+    StreamExt, TryStreamExt,
+};
 use http::{
-    header::{CONTENT_LENGTH, RANGE},
+    header::{
+        CACHE_CONTROL, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MATCH, LAST_MODIFIED,
+        RANGE,
+    },
     request::Builder,
-    Method, Request,
+    HeaderMap, Method, Request, Response,
 };
+use http_body::Body;
 use http_body_util::{BodyExt, Empty};
-use percent_encoding::utf8_percent_encode;
 
-use super::{fs::AmazonS3, sign::Sign, S3Error, STRICT_PATH_ENCODE_SET};
+use super::{
+    encryption::ServerSideEncryption, fs::AmazonS3, response_error, sign::Sign, S3Error,
+    STORAGE_CLASS_HEADER, TAGGING_COUNT_HEADER, USER_METADATA_PREFIX, VERSION_ID_HEADER,
+};
 use crate::{
     error::Error,
+    fs::{FileMeta, GetResult, OpenOptions, WriteAttributes},
     path::Path,
     remotes::{
-        aws::{multipart_upload::MultipartUpload, writer::S3Writer},
-        http::{HttpClient, HttpError},
+        aws::{
+            multipart_upload::{MultipartUpload, WriteCondition},
+            writer::S3Writer,
+        },
+        http::{BoxBody, HttpClient, HttpError},
+        serde::GetObjectTaggingResult,
     },
-    IoBuf, IoBufMut, Read, Write,
+    IoBuf, IoBufMut, MaybeSend, Read, Write,
 };
 
 pub struct S3File {
     fs: AmazonS3,
     path: Path,
+    encryption: Option<ServerSideEncryption>,
     writer: Option<S3Writer>,
+    e_tag: Option<String>,
+    version: Option<String>,
 }
 
 impl S3File {
     pub(crate) fn new(fs: AmazonS3, path: Path, create: bool) -> Self {
+        let encryption = fs.as_ref().options.encryption.clone();
+        Self::with_options(fs, path, &OpenOptions::default().create(create), encryption)
+    }
+
+    /// Creates a file that is written according to `options`, e.g. only published on close if
+    /// its condition holds, and which is encrypted with `encryption`.
+    pub(crate) fn with_options(
+        fs: AmazonS3,
+        path: Path,
+        options: &OpenOptions,
+        encryption: Option<ServerSideEncryption>,
+    ) -> Self {
         Self {
-            writer: create
-                .then(|| S3Writer::new(Arc::new(MultipartUpload::new(fs.clone(), path.clone())))),
+            writer: (options.create || options.write).then(|| {
+                S3Writer::new(Arc::new(
+                    MultipartUpload::new(fs.clone(), path.clone())
+                        .with_condition(WriteCondition::from_options(options))
+                        .with_encryption(encryption.clone())
+                        .with_attributes(options.attributes.clone()),
+                ))
+            }),
             fs,
             path,
+            encryption,
+            e_tag: None,
+            version: None,
         }
     }
 
     fn build_request(&self, method: Method) -> Builder {
-        let url = format!(
-            "{}/{}",
-            self.fs.as_ref().options.endpoint,
-            utf8_percent_encode(self.path.as_ref(), &STRICT_PATH_ENCODE_SET)
-        );
+        let url = self.fs.as_ref().options.object_url(&self.path);
+        let builder = Request::builder().method(method).uri(url);
 
-        Request::builder().method(method).uri(url)
+        match &self.encryption {
+            Some(encryption) => encryption.apply_customer_key(builder),
+            None => builder,
+        }
     }
-}
 
-impl Read for S3File {
-    async fn read_exact_at<B: IoBufMut>(&mut self, mut buf: B, pos: u64) -> (Result<(), Error>, B) {
-        let request = self
-            .build_request(Method::GET)
-            .header(
-                RANGE,
-                format!("bytes={}-{}", pos, pos + buf.as_slice().len() as u64 - 1),
-            )
-            .body(Empty::new())
-            .map_err(|e| S3Error::from(HttpError::from(e)));
+    /// Discards the content written to the file instead of publishing it, aborting its multipart
+    /// upload if one was started.
+    pub async fn abort(&mut self) -> Result<(), Error> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.abort().await?;
+        }
+        Ok(())
+    }
 
-        let mut request = match request {
-            Ok(request) => request,
-            Err(e) => return (Err(Error::Remote(Box::new(e))), buf),
-        };
+    /// Returns the number of bytes written to the file that S3 has acknowledged so far, which
+    /// trails the bytes written while parts are in flight.
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.writer.as_ref().map_or(0, S3Writer::uploaded_bytes)
+    }
 
-        if let Err(e) = request
-            .sign(&self.fs.as_ref().options)
-            .await
-            .map_err(S3Error::from)
-        {
-            return (Err(Error::Remote(e.into())), buf);
-        }
+    /// Returns the ETag S3 assigned to the content published by [`Write::close`].
+    pub fn e_tag(&self) -> Option<&str> {
+        self.e_tag.as_deref()
+    }
 
-        let response = match self
-            .fs
-            .as_ref()
-            .client
-            .send_request(request)
+    /// Returns the version S3 assigned to the content published by [`Write::close`], if the
+    /// bucket has versioning enabled.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Fetches the metadata of the object with a `HEAD` request.
+    pub async fn metadata(&self) -> Result<FileMeta, Error> {
+        let headers = self.head().await?;
+        self.file_meta(&headers)
+    }
+
+    /// Reads the whole object along with its metadata with a single `GET` request.
+    pub(crate) async fn get_object(&self) -> Result<GetResult, Error> {
+        let (parts, body) = self.get(None, None).await?.into_parts();
+        let meta = self.file_meta(&parts.headers)?;
+        let bytes = body
+            .collect()
             .await
-            .map_err(S3Error::from)
-        {
-            Ok(response) => response,
-            Err(e) => return (Err(Error::Remote(Box::new(e))), buf),
+            .map_err(|err| Error::Remote(Box::new(err)))?
+            .to_bytes();
+
+        Ok(GetResult { bytes, meta })
+    }
+
+    /// Parses the metadata of the object from the headers of a response to a `HEAD` or `GET`
+    /// request.
+    pub(crate) fn file_meta(&self, headers: &HeaderMap) -> Result<FileMeta, Error> {
+        let size = headers
+            .get(CONTENT_LENGTH)
+            .ok_or_else(|| Error::Other("missing content-length header".into()))
+            .map_err(|err| Error::Other(Box::new(err)))?
+            .to_str()
+            .map_err(|e| Error::Remote(e.into()))?
+            .parse::<u64>()
+            .map_err(|e| Error::Remote(e.into()))?;
+        let header_str = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+        Ok(FileMeta {
+            path: self.path.clone(),
+            size,
+            last_modified: header_str(LAST_MODIFIED.as_str())
+                .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+                .map(SystemTime::from),
+            e_tag: header_str(ETAG.as_str()).map(str::to_string),
+            version: header_str(VERSION_ID_HEADER).map(str::to_string),
+            is_dir: false,
+        })
+    }
+
+    /// Fetches the [`WriteAttributes`] the object was written with. The tags of the object are
+    /// fetched with a second request, if it has any.
+    pub async fn attributes(&self) -> Result<WriteAttributes, Error> {
+        self.attributes_from(&self.head().await?).await
+    }
+
+    /// Parses the [`WriteAttributes`] of the object from the headers of a response to a `HEAD`
+    /// request, fetching its tags if it has any.
+    pub(crate) async fn attributes_from(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<WriteAttributes, Error> {
+        let header_string = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let metadata = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let key = name.as_str().strip_prefix(USER_METADATA_PREFIX)?;
+                Some((key.to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+        let tags = match header_string(TAGGING_COUNT_HEADER) {
+            Some(count) if count != "0" => self.tags().await?,
+            _ => BTreeMap::new(),
         };
 
-        if !response.status().is_success() {
-            return (
-                Err(Error::Remote(Box::new(HttpError::HttpNotSuccess {
-                    status: response.status(),
-                    body: String::from_utf8_lossy(
-                        &response
-                            .into_body()
-                            .collect()
-                            .await
-                            .map(|b| b.to_bytes())
-                            .unwrap_or_default(),
-                    )
-                    .to_string(),
-                }))),
-                buf,
-            );
-        } else {
-            match response.into_body().collect().await.map_err(S3Error::from) {
-                Ok(body) => {
-                    if let Err(e) = std::io::Read::read_exact(
-                        &mut body.aggregate().reader(),
-                        buf.as_slice_mut(),
-                    ) {
-                        return (Err(e.into()), buf);
-                    }
-                }
-                Err(e) => return (Err(Error::Remote(Box::new(e))), buf),
-            }
+        Ok(WriteAttributes {
+            content_type: header_string(CONTENT_TYPE.as_str()),
+            cache_control: header_string(CACHE_CONTROL.as_str()),
+            metadata,
+            storage_class: header_string(STORAGE_CLASS_HEADER),
+            tags,
+        })
+    }
 
-            (Ok(()), buf)
-        }
+    /// Fetches the tags of the object with a `GetObjectTagging` request.
+    async fn tags(&self) -> Result<BTreeMap<String, String>, Error> {
+        let url = format!(
+            "{}?tagging",
+            self.fs.as_ref().options.object_url(&self.path)
+        );
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(url)
+            .body(Empty::new())
+            .map_err(|e| Error::Remote(Box::new(HttpError::from(e))))?;
+        let body = self
+            .send(request)
+            .await?
+            .into_body()
+            .collect()
+            .await
+            .map_err(|err| Error::Remote(Box::new(err)))?
+            .aggregate();
+        let result: GetObjectTaggingResult = quick_xml::de::from_reader(body.reader())
+            .map_err(|err| Error::Remote(Box::new(S3Error::from(err))))?;
+
+        Ok(result
+            .tag_set
+            .tag
+            .into_iter()
+            .map(|tag| (tag.key, tag.value))
+            .collect())
     }
 
-    async fn read_to_end_at(&mut self, mut buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
-        let mut request = match self
-            .build_request(Method::GET)
-            .header(RANGE, format!("bytes={}-", pos))
+    /// Sends a `HEAD` request for the object and returns the headers of the response.
+    pub(crate) async fn head(&self) -> Result<HeaderMap, Error> {
+        let request = self
+            .build_request(Method::HEAD)
             .body(Empty::new())
-            .map_err(|e| S3Error::from(HttpError::from(e)))
-        {
-            Err(e) => return (Err(Error::Remote(Box::new(e))), buf),
-            Ok(request) => request,
+            .map_err(|e| Error::Remote(Box::new(HttpError::from(e))))?;
+
+        Ok(self.send(request).await?.into_parts().0.headers)
+    }
+
+    /// Reads the bytes at `pos` into `dst` with concurrent ranged requests, each of at most the
+    /// read part size, which only succeed while the ETag of the object is `e_tag`. Without an
+    /// `e_tag`, the first part is read alone and the others are pinned to the ETag it returned.
+    async fn read_ranges(
+        &self,
+        dst: &mut [u8],
+        pos: u64,
+        e_tag: Option<&str>,
+    ) -> Result<(), Error> {
+        let options = &self.fs.as_ref().options;
+        let part_size = options.read_part_size;
+
+        let (dst, pos, e_tag) = match e_tag {
+            Some(e_tag) => (dst, pos, Some(e_tag.to_string())),
+            None => {
+                let (first, rest) = dst.split_at_mut(part_size.min(dst.len()));
+                let e_tag = self.read_range(first, pos, None).await?;
+                (rest, pos + first.len() as u64, e_tag)
+            }
         };
+        let e_tag = e_tag.as_deref();
+
+        let mut parts = dst.chunks_mut(part_size).enumerate();
+        let mut in_flight = FuturesUnordered::new();
+        loop {
+            while in_flight.len() < options.max_in_flight_reads {
+                let Some((part_num, part)) = parts.next() else {
+                    break;
+                };
+                in_flight.push(self.read_range(part, pos + (part_num * part_size) as u64, e_tag));
+            }
+            match in_flight.next().await {
+                Some(result) => {
+                    result?;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
 
-        if let Err(e) = request
-            .sign(&self.fs.as_ref().options)
-            .await
-            .map_err(S3Error::from)
-        {
-            return (Err(Error::Other(Box::new(e))), buf);
+    /// Reads `len` bytes at `pos` with a ranged `GET` request. The frames of the response body
+    /// are handed out as they arrive, without being copied or collected, and the stream fails if
+    /// the body is shorter or longer than `len`.
+    pub async fn read_frames_at(
+        &self,
+        pos: u64,
+        len: u64,
+    ) -> Result<impl Stream<Item = Result<Bytes, Error>>, Error> {
+        self.frames(pos, len, None).await
+    }
+
+    async fn frames(&self, pos: u64, len: u64, e_tag: Option<&str>) -> Result<Frames, Error> {
+        if len == 0 {
+            return Ok(Frames {
+                body: None,
+                remaining: 0,
+            });
         }
+        let range = format!("bytes={}-{}", pos, pos + len - 1);
+        let body = self.get(Some(range), e_tag).await?.into_body();
 
-        let response = match self
-            .fs
-            .as_ref()
-            .client
-            .send_request(request)
-            .await
-            .map_err(S3Error::from)
-        {
-            Ok(response) => response,
-            Err(e) => return (Err(Error::Remote(Box::new(e))), buf),
+        Ok(Frames {
+            body: Some(body),
+            remaining: len,
+        })
+    }
+
+    /// Reads the bytes at `pos` into the non-empty `dst` with a single ranged request, copying
+    /// each frame of the response body into place as it arrives, and returns the ETag of the
+    /// object read.
+    async fn read_range(
+        &self,
+        dst: &mut [u8],
+        pos: u64,
+        e_tag: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        let range = format!("bytes={}-{}", pos, pos + dst.len() as u64 - 1);
+        let response = self.get(Some(range), e_tag).await?;
+        let e_tag = response_e_tag(response.headers());
+        let mut frames = Frames {
+            body: Some(response.into_body()),
+            remaining: dst.len() as u64,
         };
 
-        if !response.status().is_success() {
-            return (
-                Err(Error::Remote(Box::new(HttpError::HttpNotSuccess {
-                    status: response.status(),
-                    body: String::from_utf8_lossy(
-                        &response
-                            .into_body()
-                            .collect()
-                            .await
-                            .map(|b| b.to_bytes())
-                            .unwrap_or_default(),
-                    )
-                    .to_string(),
-                }))),
-                buf,
-            );
-        } else {
-            match response.into_body().collect().await.map_err(S3Error::from) {
-                Ok(body) => {
-                    let mut body = body.to_bytes();
-                    buf.resize(body.len(), 0);
-                    body.copy_to_slice(&mut buf[..]);
-                    (Ok(()), buf)
-                }
-                Err(e) => (Err(Error::Remote(Box::new(e))), buf),
-            }
+        let mut filled = 0;
+        while let Some(data) = frames.next().await {
+            let data = data?;
+            dst[filled..filled + data.len()].copy_from_slice(&data);
+            filled += data.len();
         }
+        Ok(e_tag)
     }
 
-    async fn size(&self) -> Result<u64, Error> {
-        let mut request = self
-            .build_request(Method::HEAD)
+    /// Sends a `GET` request for `range` of the object, or all of it, only if its ETag is `e_tag`
+    /// if given.
+    async fn get(
+        &self,
+        range: Option<String>,
+        e_tag: Option<&str>,
+    ) -> Result<Response<BoxBody>, Error> {
+        let mut builder = self.build_request(Method::GET);
+        if let Some(range) = range {
+            builder = builder.header(RANGE, range);
+        }
+        if let Some(e_tag) = e_tag {
+            builder = builder.header(IF_MATCH, e_tag);
+        }
+        let request = builder
             .body(Empty::new())
             .map_err(|e| Error::Remote(Box::new(HttpError::from(e))))?;
+
+        self.send(request).await
+    }
+
+    /// Signs and sends `request`, failing if it was not successful.
+    async fn send(&self, mut request: Request<Empty<Bytes>>) -> Result<Response<BoxBody>, Error> {
         request
             .sign(&self.fs.as_ref().options)
             .await
@@ -192,12 +371,12 @@ impl Read for S3File {
             .client
             .send_request(request)
             .await
-            .map_err(|err| Error::Remote(Box::new(err)))?;
+            .map_err(Error::from)?;
 
         if !response.status().is_success() {
-            Err(Error::Other(Box::new(HttpError::HttpNotSuccess {
-                status: response.status(),
-                body: String::from_utf8_lossy(
+            Err(response_error(
+                response.status(),
+                String::from_utf8_lossy(
                     &response
                         .into_body()
                         .collect()
@@ -206,20 +385,148 @@ impl Read for S3File {
                         .to_bytes(),
                 )
                 .to_string(),
-            })))
+            ))
         } else {
-            let size = response
-                .headers()
-                .get(CONTENT_LENGTH)
-                .ok_or_else(|| Error::Other("missing content-length header".into()))
-                .map_err(|err| Error::Other(Box::new(err)))?
-                .to_str()
-                .map_err(|e| Error::Remote(e.into()))?
-                .parse::<u64>()
-                .map_err(|e| Error::Remote(e.into()))?;
-            Ok(size)
+            Ok(response)
+        }
+    }
+}
+
+/// The data frames of the response to a ranged request, which end with an error unless they
+/// add up to the `remaining` bytes of the range.
+struct Frames {
+    body: Option<BoxBody>,
+    remaining: u64,
+}
+
+impl Stream for Frames {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let Some(body) = self.body.as_mut() else {
+                return Poll::Ready(None);
+            };
+            let result = match ready!(Pin::new(body).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) if data.len() as u64 > self.remaining => Err(Error::Other(
+                        format!(
+                            "response body is longer than the {} remaining bytes of the range",
+                            self.remaining
+                        )
+                        .into(),
+                    )),
+                    Ok(data) if data.is_empty() => continue,
+                    Ok(data) => {
+                        self.remaining -= data.len() as u64;
+                        return Poll::Ready(Some(Ok(data)));
+                    }
+                    // trailers carry no data
+                    Err(_) => continue,
+                },
+                Some(Err(err)) => Err(Error::Remote(Box::new(err))),
+                None if self.remaining > 0 => Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "response body ended {} bytes before the end of the range",
+                        self.remaining
+                    ),
+                ))),
+                None => Ok(()),
+            };
+            self.body = None;
+
+            return Poll::Ready(result.err().map(Err));
+        }
+    }
+}
+
+/// Returns the ETag of the object a response was served from.
+fn response_e_tag(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Appends the data of `body` to `buf` as it arrives.
+async fn extend_from_body(buf: &mut Vec<u8>, mut body: BoxBody) -> Result<(), Error> {
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|err| Error::Remote(Box::new(err)))?;
+        if let Ok(data) = frame.into_data() {
+            buf.extend_from_slice(&data);
         }
     }
+    Ok(())
+}
+
+impl Read for S3File {
+    /// Reads the bytes at `pos` with one ranged `GET` request, or with concurrent requests of
+    /// the read part size if `buf` is larger than it. These only succeed while the object is
+    /// unchanged since the first of them.
+    async fn read_exact_at<B: IoBufMut>(&mut self, mut buf: B, pos: u64) -> (Result<(), Error>, B) {
+        let len = buf.bytes_init();
+        let result = if len == 0 {
+            Ok(())
+        } else if len <= self.fs.as_ref().options.read_part_size {
+            self.read_range(buf.as_slice_mut(), pos, None)
+                .await
+                .map(|_| ())
+        } else {
+            self.read_ranges(buf.as_slice_mut(), pos, None).await
+        };
+
+        (result, buf)
+    }
+
+    /// Reads the bytes from `pos` to the end of the object into `buf`, replacing its content.
+    ///
+    /// The first part is read with a single request, which tells the size of the object. The
+    /// rest, if any, is read with concurrent requests that only succeed while the object is
+    /// unchanged.
+    async fn read_to_end_at(&mut self, mut buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
+        buf.clear();
+        let result = async {
+            let part_size = self.fs.as_ref().options.read_part_size as u64;
+            let response = self
+                .get(Some(format!("bytes={}-{}", pos, pos + part_size - 1)), None)
+                .await?;
+            let headers = response.headers();
+            let size = headers
+                .get(CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit_once('/'))
+                .and_then(|(_, size)| size.parse::<u64>().ok());
+            let e_tag = response_e_tag(headers);
+            extend_from_body(&mut buf, response.into_body()).await?;
+
+            let read = pos + buf.len() as u64;
+            if let Some(size) = size.filter(|size| *size > read) {
+                let offset = buf.len();
+                buf.resize((size - pos) as usize, 0);
+                self.read_ranges(&mut buf[offset..], read, e_tag.as_deref())
+                    .await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        (result, buf)
+    }
+
+    async fn size(&self) -> Result<u64, Error> {
+        self.metadata().await.map(|meta| meta.size)
+    }
+
+    /// Streams the body of a single ranged `GET` request, see [`S3File::read_frames_at`].
+    fn read_stream(
+        &mut self,
+        range: Range<u64>,
+    ) -> impl Stream<Item = Result<Bytes, Error>> + MaybeSend + '_ {
+        let len = range.end.saturating_sub(range.start);
+
+        stream::once(self.frames(range.start, len, None)).try_flatten()
+    }
 }
 
 impl Write for S3File {
@@ -241,6 +548,8 @@ impl Write for S3File {
     async fn close(&mut self) -> Result<(), Error> {
         if let Some(mut writer) = self.writer.take() {
             writer.close().await?;
+            self.e_tag = writer.e_tag().map(str::to_string);
+            self.version = writer.version().map(str::to_string);
         }
         Ok(())
     }
@@ -290,20 +599,25 @@ mod tests {
         let options = S3Options {
             endpoint: format!("https://{}.s3.{}.amazonaws.com", &bucket, &region),
             bucket,
-            credential: Some(AwsCredential {
+            credential: Some(Arc::new(AwsCredential {
                 key_id,
                 secret_key,
                 token,
-            }),
+            })),
             region,
             sign_payload: true,
             checksum: false,
+            encryption: None,
+            part_size: 5 * 1024 * 1024,
+            max_in_flight_parts: 8,
+            read_part_size: 8 * 1024 * 1024,
+            max_in_flight_reads: 8,
         };
 
         let s3 = AmazonS3 {
             inner: Arc::new(AmazonS3Inner {
                 options,
-                client: Box::new(client) as Box<dyn DynHttpClient>,
+                client: Arc::new(client) as Arc<dyn DynHttpClient>,
             }),
         };
 
@@ -381,4 +695,188 @@ mod tests {
         result.unwrap();
         assert_eq!(buf, b"The answer of life, universe and everthing");
     }
+
+    #[cfg(feature = "tokio-http")]
+    #[tokio::test]
+    async fn encryption_of_requests() {
+        use http::Method;
+
+        use crate::{
+            fs::OpenOptions,
+            path::Path,
+            remotes::aws::{fs::AmazonS3Builder, s3::S3File, ServerSideEncryption},
+        };
+
+        let s3 = AmazonS3Builder::new("data".into())
+            .encryption(ServerSideEncryption::Customer { key: [1; 32] })
+            .build();
+        let path = Path::from("encrypted");
+        let customer_headers = |file: &S3File| {
+            let request = file.build_request(Method::GET).body(()).unwrap();
+            request
+                .headers()
+                .keys()
+                .filter(|name| name.as_str().starts_with("x-amz-server-side-encryption"))
+                .count()
+        };
+
+        let file = S3File::new(s3.clone(), path.clone(), false);
+        assert_eq!(customer_headers(&file), 3);
+
+        // keys managed by S3 or KMS are not sent along with reads
+        let file = s3
+            .open_with_encryption(&path, OpenOptions::default(), ServerSideEncryption::S3)
+            .await
+            .unwrap();
+        assert_eq!(customer_headers(&file), 0);
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn ranged_reads() {
+        use http::{Method, Response, StatusCode};
+
+        use crate::{
+            remotes::aws::{fs::AmazonS3, options::S3Options, s3::S3File, stand_in::StandIn},
+            Read,
+        };
+
+        const OBJECT: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+        let bucket = StandIn::with_headers(|_, _, headers| {
+            let (start, end) = headers["range"]
+                .to_str()
+                .unwrap()
+                .strip_prefix("bytes=")
+                .and_then(|range| range.split_once('-'))
+                .map(|(start, end)| {
+                    (
+                        start.parse::<usize>().unwrap(),
+                        end.parse::<usize>().unwrap(),
+                    )
+                })
+                .unwrap();
+            let end = end.min(OBJECT.len() - 1);
+            Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    "Content-Range",
+                    format!("bytes {start}-{end}/{}", OBJECT.len()),
+                )
+                .header("ETag", "\"object\"")
+                .body(String::from_utf8(OBJECT[start..=end].to_vec()).unwrap())
+                .unwrap()
+        });
+        let options = S3Options {
+            read_part_size: 4,
+            max_in_flight_reads: 2,
+            ..bucket.s3().inner.options.clone()
+        };
+        let mut file = S3File::new(
+            AmazonS3::new(Box::new(bucket.clone()), options),
+            "object".into(),
+            false,
+        );
+        let ranges = || {
+            let mut requests = bucket
+                .requests_with_headers()
+                .into_iter()
+                .map(|(method, _, headers)| {
+                    assert_eq!(method, Method::GET);
+                    (
+                        headers["range"].to_str().unwrap().to_string(),
+                        headers.contains_key("if-match"),
+                    )
+                })
+                .collect::<Vec<_>>();
+            requests.sort();
+            requests
+        };
+
+        let (result, buf) = file.read_exact_at(vec![0; 3], 3).await;
+        result.unwrap();
+        assert_eq!(buf, b"345");
+        assert_eq!(ranges(), [("bytes=3-5".into(), false)]);
+
+        let (result, buf) = file.read_exact_at(vec![0; 10], 3).await;
+        result.unwrap();
+        assert_eq!(buf, &OBJECT[3..13]);
+        assert_eq!(
+            ranges(),
+            [
+                ("bytes=11-12".into(), true),
+                ("bytes=3-6".into(), false),
+                ("bytes=7-10".into(), true),
+            ]
+        );
+
+        let (result, buf) = file.read_to_end_at(b"stale".to_vec(), 30).await;
+        result.unwrap();
+        assert_eq!(buf, &OBJECT[30..]);
+        assert_eq!(ranges().len(), 2);
+
+        // the ranges after the first are only read from the same version of the object
+        let (result, buf) = file.read_to_end_at(Vec::new(), 33).await;
+        result.unwrap();
+        assert_eq!(buf, b"xyz");
+        assert_eq!(ranges(), [("bytes=33-36".into(), false)]);
+        let (result, buf) = file.read_to_end_at(Vec::new(), 1).await;
+        result.unwrap();
+        assert_eq!(buf, &OBJECT[1..]);
+        let ranges = ranges();
+        assert_eq!(ranges.len(), 9);
+        assert_eq!(ranges.iter().filter(|(_, if_match)| !if_match).count(), 1);
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn streamed_reads() {
+        use std::io;
+
+        use futures_util::TryStreamExt;
+        use http::Response;
+
+        use crate::{
+            error::Error,
+            remotes::aws::{s3::S3File, stand_in::StandIn},
+            Read,
+        };
+
+        let bucket = StandIn::new(|_, _| Response::new("abcd".into()));
+        let mut file = S3File::new(bucket.s3(), "object".into(), false);
+
+        let frames = file
+            .read_frames_at(0, 4)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(frames, ["abcd"]);
+        assert_eq!(bucket.requests().len(), 1);
+        let chunks = file
+            .read_stream(0..4)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(chunks, ["abcd"]);
+        assert_eq!(bucket.requests()[0].1, "/data/object");
+        let frames = file.read_frames_at(0, 0).await.unwrap();
+        assert_eq!(frames.try_collect::<Vec<_>>().await.unwrap().len(), 0);
+        assert!(bucket.requests().is_empty());
+
+        let result = file
+            .read_frames_at(0, 3)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await;
+        assert!(matches!(result, Err(Error::Other(_))));
+
+        let (result, _) = file.read_exact_at(vec![0; 5], 0).await;
+        assert!(matches!(
+            result,
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
 }
@@ -43,13 +43,16 @@ where
     async fn sign(&mut self, options: &S3Options) -> Result<(), AuthorizeError> {
         self.checksum(options).await?;
 
-        let credential = if let Some(credential) = options.credential.as_ref() {
-            credential
-        } else {
+        let Some(provider) = options.credential.as_ref() else {
             return Ok(());
         };
+        let credential = provider
+            .dyn_credential()
+            .await
+            .map_err(AuthorizeError::Credential)?
+            .token;
 
-        let authorizer = AwsAuthorizer::new(credential, "s3", &options.region).with_sign_payload(
+        let authorizer = AwsAuthorizer::new(&credential, "s3", &options.region).with_sign_payload(
             if options.checksum {
                 false
             } else {
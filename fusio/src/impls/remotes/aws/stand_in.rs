@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response};
+use http_body::Body;
+use http_body_util::Full;
+
+use super::{fs::AmazonS3, options::S3Options};
+use crate::{
+    error::BoxedError,
+    remotes::http::{HttpClient, HttpError},
+    MaybeSync,
+};
+
+type Respond = dyn Fn(&Method, &str, &HeaderMap) -> Response<String> + Send + Sync;
+
+/// Answers requests in place of a bucket or another AWS endpoint, by calling `respond` with the
+/// method and the path and query of each request, and records the requests it received.
+#[derive(Clone)]
+pub(crate) struct StandIn {
+    respond: Arc<Respond>,
+    requests: Arc<Mutex<Vec<(Method, String, HeaderMap)>>>,
+}
+
+impl StandIn {
+    pub(crate) fn new(
+        respond: impl Fn(&Method, &str) -> Response<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self::with_headers(move |method, target, _| respond(method, target))
+    }
+
+    /// Creates a stand-in like [`StandIn::new`] whose `respond` also sees the request headers.
+    pub(crate) fn with_headers(
+        respond: impl Fn(&Method, &str, &HeaderMap) -> Response<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            respond: Arc::new(respond),
+            requests: Arc::default(),
+        }
+    }
+
+    /// Returns a file system of the bucket `data` whose requests are answered by this stand-in.
+    pub(crate) fn s3(&self) -> AmazonS3 {
+        AmazonS3::new(
+            Box::new(self.clone()),
+            S3Options {
+                endpoint: "http://localhost:9000/data".into(),
+                bucket: "data".into(),
+                region: "us-east-1".into(),
+                credential: None,
+                sign_payload: false,
+                checksum: false,
+                encryption: None,
+                part_size: 5 * 1024 * 1024,
+                max_in_flight_parts: 8,
+                read_part_size: 8 * 1024 * 1024,
+                max_in_flight_reads: 8,
+            },
+        )
+    }
+
+    /// Returns the requests received since the last call.
+    pub(crate) fn requests(&self) -> Vec<(Method, String)> {
+        self.requests_with_headers()
+            .into_iter()
+            .map(|(method, target, _)| (method, target))
+            .collect()
+    }
+
+    /// Returns the requests received since the last call, along with their headers.
+    pub(crate) fn requests_with_headers(&self) -> Vec<(Method, String, HeaderMap)> {
+        std::mem::take(&mut self.requests.lock().unwrap())
+    }
+}
+
+impl HttpClient for StandIn {
+    type RespBody = Full<Bytes>;
+
+    async fn send_request<B>(
+        &self,
+        request: Request<B>,
+    ) -> Result<Response<Self::RespBody>, HttpError>
+    where
+        B: Body + Send + MaybeSync + 'static,
+        B::Data: Into<Bytes>,
+        B::Error: Into<BoxedError>,
+    {
+        let target = request
+            .uri()
+            .path_and_query()
+            .map(|target| target.as_str().to_string())
+            .unwrap_or_default();
+        let response = (self.respond)(request.method(), &target, request.headers());
+        self.requests.lock().unwrap().push((
+            request.method().clone(),
+            target,
+            request.headers().clone(),
+        ));
+
+        Ok(response.map(|body| Full::new(Bytes::from(body))))
+    }
+}
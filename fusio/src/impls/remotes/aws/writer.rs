@@ -1,47 +1,149 @@
-use std::{mem, pin::Pin, sync::Arc};
+use std::{future::Future, pin::Pin, sync::Arc};
 
-use bytes::{BufMut, BytesMut};
-use fusio_core::MaybeSendFuture;
+use bytes::{BufMut, Bytes, BytesMut};
+use fusio_core::{MaybeSend, MaybeSendFuture};
 use futures_util::{stream::FuturesOrdered, StreamExt};
 use http_body_util::Full;
 
 use crate::{
     error::Error,
     remotes::{
-        aws::multipart_upload::{MultipartUpload, UploadType},
+        aws::multipart_upload::{MultipartUpload, Published, UploadType},
         serde::MultipartPart,
     },
     IoBuf, Write,
 };
 
-const S3_PART_MINIMUM_SIZE: usize = 5 * 1024 * 1024;
+pub(crate) const S3_PART_MINIMUM_SIZE: usize = 5 * 1024 * 1024;
+/// The maximum part size of 5 GiB, or the largest buffer on targets that cannot hold one.
+pub(crate) const S3_PART_MAXIMUM_SIZE: usize = if usize::BITS > 32 {
+    (5u64 * 1024 * 1024 * 1024) as usize
+} else {
+    usize::MAX
+};
+pub(crate) const S3_MAXIMUM_PARTS: usize = 10_000;
+/// The number of parts after which the part size doubles, so that the parts of an object of
+/// unknown size cover the maximum object size of 5 TiB.
+const PARTS_PER_SIZE: usize = 1_000;
+
+type PartFuture = Pin<Box<dyn MaybeSendFuture<Output = Result<(MultipartPart, u64), Error>>>>;
 
+/// Uploads the content written to an object, with a multipart upload once it outgrows a part.
+///
+/// At most [`AmazonS3Builder::max_in_flight_parts`] parts are uploaded at once; writes wait for
+/// one of them to finish before starting another.
+///
+/// Dropping a writer that was neither closed nor aborted aborts its multipart upload in the
+/// background on a current tokio runtime or in the browser. Otherwise, e.g. on monoio, the upload
+/// is left to
+/// [`AmazonS3::abort_stale_uploads`](crate::remotes::aws::fs::AmazonS3::abort_stale_uploads).
+///
+/// [`AmazonS3Builder::max_in_flight_parts`]: crate::remotes::aws::fs::AmazonS3Builder::max_in_flight_parts
 pub struct S3Writer {
     inner: Arc<MultipartUpload>,
     upload_id: Option<Arc<String>>,
     next_part_numer: usize,
     buf: BytesMut,
+    part_size: usize,
+    max_in_flight_parts: usize,
+    parts: Vec<MultipartPart>,
+    uploaded_bytes: u64,
+    published: Published,
 
-    handlers: FuturesOrdered<Pin<Box<dyn MaybeSendFuture<Output = Result<MultipartPart, Error>>>>>,
+    handlers: FuturesOrdered<PartFuture>,
 }
 
 unsafe impl Sync for S3Writer {}
 
 impl S3Writer {
     pub fn new(inner: Arc<MultipartUpload>) -> Self {
+        let options = inner.options();
+        let part_size = options.part_size;
+        let max_in_flight_parts = options.max_in_flight_parts;
+
         Self {
             inner,
             upload_id: None,
             next_part_numer: 0,
-            buf: BytesMut::with_capacity(S3_PART_MINIMUM_SIZE),
+            buf: BytesMut::new(),
+            part_size,
+            max_in_flight_parts,
+            parts: Vec::new(),
+            uploaded_bytes: 0,
+            published: Published::default(),
             handlers: FuturesOrdered::new(),
         }
     }
 
-    async fn upload_part<F>(&mut self, fn_bytes_init: F) -> Result<(), Error>
-    where
-        F: FnOnce() -> BytesMut,
-    {
+    /// Returns the number of written bytes S3 has acknowledged so far.
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes
+    }
+
+    /// Returns the ETag S3 assigned to the object once the writer is closed.
+    pub fn e_tag(&self) -> Option<&str> {
+        self.published.e_tag.as_deref()
+    }
+
+    /// Returns the version S3 assigned to the object once the writer is closed, if the bucket
+    /// has versioning enabled.
+    pub fn version(&self) -> Option<&str> {
+        self.published.version.as_deref()
+    }
+
+    /// Discards the written content, aborting the multipart upload if one was started.
+    pub async fn abort(&mut self) -> Result<(), Error> {
+        self.buf.clear();
+        // the parts are only uploaded while the handlers are polled
+        self.handlers = FuturesOrdered::new();
+        self.parts.clear();
+        match self.upload_id.take() {
+            Some(upload_id) => self.inner.abort(&upload_id).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the size of the next part, which doubles every [`PARTS_PER_SIZE`] parts.
+    fn next_part_size(&self) -> usize {
+        let doublings = (self.next_part_numer / PARTS_PER_SIZE).min(usize::BITS as usize - 1);
+
+        self.part_size
+            .saturating_mul(1 << doublings)
+            .min(S3_PART_MAXIMUM_SIZE)
+    }
+
+    /// Uploads the remaining parts and completes the multipart upload.
+    async fn complete(&mut self, upload_id: &str) -> Result<(), Error> {
+        if !self.buf.is_empty() {
+            let bytes = self.buf.split().freeze();
+            self.upload_part(bytes).await?;
+        }
+        while !self.handlers.is_empty() {
+            self.acknowledge_part().await?;
+        }
+        assert_eq!(self.next_part_numer, self.parts.len());
+        self.published = self.inner.complete_part(upload_id, &self.parts).await?;
+        self.upload_id = None;
+
+        Ok(())
+    }
+
+    /// Waits for the oldest part in flight to be uploaded.
+    async fn acknowledge_part(&mut self) -> Result<(), Error> {
+        if let Some(result) = self.handlers.next().await {
+            let (part, size) = result.map_err(|err| Error::Remote(Box::new(err)))?;
+            self.parts.push(part);
+            self.uploaded_bytes += size;
+        }
+        Ok(())
+    }
+
+    async fn upload_part(&mut self, bytes: Bytes) -> Result<(), Error> {
+        if self.next_part_numer == S3_MAXIMUM_PARTS {
+            return Err(Error::Unsupported {
+                message: format!("S3 multipart uploads have at most {S3_MAXIMUM_PARTS} parts"),
+            });
+        }
         let upload_id = match self.upload_id.clone() {
             None => {
                 let upload_id = Arc::new(
@@ -55,15 +157,19 @@ impl S3Writer {
             }
             Some(upload_id) => upload_id,
         };
+        while self.handlers.len() >= self.max_in_flight_parts {
+            self.acknowledge_part().await?;
+        }
         let part_num = self.next_part_numer;
         self.next_part_numer += 1;
 
         let upload = self.inner.clone();
-        let bytes = mem::replace(&mut self.buf, fn_bytes_init()).freeze();
         self.handlers.push_back(Box::pin(async move {
-            upload
-                .upload_part(&upload_id, part_num, bytes.len(), Full::new(bytes))
-                .await
+            let size = bytes.len();
+            let part = upload
+                .upload_part(&upload_id, part_num, size, Full::new(bytes))
+                .await?;
+            Ok((part, size as u64))
         }));
 
         Ok(())
@@ -72,53 +178,83 @@ impl S3Writer {
 
 impl Write for S3Writer {
     async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
-        if self.buf.len() > S3_PART_MINIMUM_SIZE {
-            if let Err(e) = self
-                .upload_part(|| BytesMut::with_capacity(S3_PART_MINIMUM_SIZE))
-                .await
-            {
+        self.buf.put(buf.as_slice());
+        // a buffer of exactly one part is kept, as it might be uploaded at once on close
+        while self.buf.len() > self.next_part_size() {
+            let bytes = self.buf.split_to(self.next_part_size()).freeze();
+            if let Err(e) = self.upload_part(bytes).await {
                 return (Err(e), buf);
             }
         }
-        self.buf.put(buf.as_slice());
 
         (Ok(()), buf)
     }
 
     async fn flush(&mut self) -> Result<(), Error> {
-        if self.buf.len() > S3_PART_MINIMUM_SIZE {
-            self.upload_part(BytesMut::new).await?;
+        if self.buf.len() >= S3_PART_MINIMUM_SIZE {
+            let bytes = self.buf.split().freeze();
+            self.upload_part(bytes).await?;
         }
 
         Ok(())
     }
 
+    /// Publishes the written content. A multipart upload that fails to complete is aborted, so
+    /// its parts do not linger.
     async fn close(&mut self) -> Result<(), Error> {
         let Some(upload_id) = self.upload_id.clone() else {
-            let bytes = mem::replace(&mut self.buf, BytesMut::new()).freeze();
+            let bytes = self.buf.split().freeze();
+            let size = bytes.len();
 
-            self.inner
+            self.published = self
+                .inner
                 .upload_once(UploadType::Write {
-                    size: bytes.len(),
+                    size,
                     body: Full::new(bytes),
                 })
                 .await?;
+            self.uploaded_bytes += size as u64;
             return Ok(());
         };
-        if !self.buf.is_empty() {
-            self.upload_part(BytesMut::new).await?;
+        let result = self.complete(&upload_id).await;
+        if result.is_err() {
+            // the error of the upload matters more than whether it could be cleaned up
+            let _ = self.abort().await;
         }
-        let mut parts = Vec::with_capacity(self.handlers.len());
-        while let Some(handle) = self.handlers.next().await {
-            parts.push(handle.map_err(|err| Error::Remote(Box::new(err)))?);
+
+        result
+    }
+}
+
+impl Drop for S3Writer {
+    fn drop(&mut self) {
+        if let Some(upload_id) = self.upload_id.take() {
+            let upload = self.inner.clone();
+            spawn_detached(async move {
+                let _ = upload.abort(&upload_id).await;
+            });
         }
-        assert_eq!(self.next_part_numer, parts.len());
-        self.inner
-            .complete_part(&upload_id, &parts)
-            .await
-            .map_err(|err| Error::Remote(Box::new(err)))?;
+    }
+}
 
-        Ok(())
+/// Runs `future` in the background on the current runtime, or drops it if there is none.
+fn spawn_detached(future: impl Future<Output = ()> + MaybeSend + 'static) {
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_arch = "wasm32", any(feature = "opfs", feature = "wasm-http")))] {
+            wasm_bindgen_futures::spawn_local(future);
+        } else if #[cfg(all(
+            feature = "tokio",
+            not(feature = "completion-based"),
+            not(feature = "no-send")
+        ))] {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(future);
+            }
+        } else {
+            // monoio can not tell whether a runtime is current, and `monoio::spawn` panics
+            // outside of one, e.g. when a writer is dropped after its runtime shut down
+            drop(future);
+        }
     }
 }
 
@@ -154,21 +290,26 @@ mod tests {
         let options = S3Options {
             endpoint: "http://localhost:9000/data".into(),
             bucket: "data".to_string(),
-            credential: Some(AwsCredential {
+            credential: Some(Arc::new(AwsCredential {
                 key_id: "user".to_string(),
                 secret_key: "password".to_string(),
                 token: None,
-            }),
+            })),
             region: region.into(),
             sign_payload: true,
             checksum: false,
+            encryption: None,
+            part_size: 5 * 1024 * 1024,
+            max_in_flight_parts: 8,
+            read_part_size: 8 * 1024 * 1024,
+            max_in_flight_reads: 8,
         };
         let client = crate::impls::remotes::http::tokio::TokioClient::new();
 
         let s3 = AmazonS3 {
             inner: Arc::new(AmazonS3Inner {
                 options,
-                client: Box::new(client) as Box<dyn DynHttpClient>,
+                client: Arc::new(client) as Arc<dyn DynHttpClient>,
             }),
         };
 
@@ -181,4 +322,119 @@ mod tests {
         result.unwrap();
         writer.close().await.unwrap();
     }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn abort_multipart_upload() {
+        use std::{sync::Arc, time::Duration};
+
+        use http::{Method, Response, StatusCode};
+
+        use super::{S3Writer, S3_PART_MINIMUM_SIZE};
+        use crate::{
+            remotes::aws::{multipart_upload::MultipartUpload, stand_in::StandIn},
+            Write,
+        };
+
+        let bucket = StandIn::new(|method, target| {
+            let response = Response::builder();
+            match *method {
+                Method::POST => response.body(
+                    "<InitiateMultipartUploadResult><UploadId>1</UploadId>\
+                     </InitiateMultipartUploadResult>"
+                        .into(),
+                ),
+                Method::PUT if target.contains("partNumber=2") => response
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(String::new()),
+                Method::PUT => response.header("ETag", "\"part\"").body(String::new()),
+                _ => response.status(StatusCode::NO_CONTENT).body(String::new()),
+            }
+            .unwrap()
+        });
+        let writer = || S3Writer::new(Arc::new(MultipartUpload::new(bucket.s3(), "a".into())));
+        let start_upload = |mut writer: S3Writer| async move {
+            let (result, _) = writer.write_all(vec![0; S3_PART_MINIMUM_SIZE + 1]).await;
+            result.unwrap();
+            let (result, _) = writer.write_all(vec![0; 1]).await;
+            result.unwrap();
+            writer
+        };
+        let abort = (Method::DELETE, "/data/a?uploadId=1".to_string());
+
+        let mut aborted = start_upload(writer()).await;
+        aborted.abort().await.unwrap();
+        assert_eq!(
+            bucket.requests(),
+            [(Method::POST, "/data/a?uploads".into()), abort.clone()]
+        );
+
+        // the upload of the second part fails
+        let mut failed = start_upload(writer()).await;
+        assert!(failed.close().await.is_err());
+        assert_eq!(bucket.requests().last(), Some(&abort));
+
+        drop(start_upload(writer()).await);
+        let mut requests = bucket.requests();
+        for _ in 0..100 {
+            if requests.contains(&abort) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            requests.extend(bucket.requests());
+        }
+        assert!(requests.contains(&abort));
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn bounded_parts_in_flight() {
+        use std::sync::Arc;
+
+        use http::{Method, Response};
+
+        use super::{S3Writer, S3_PART_MAXIMUM_SIZE, S3_PART_MINIMUM_SIZE};
+        use crate::{
+            remotes::aws::{multipart_upload::MultipartUpload, stand_in::StandIn},
+            Write,
+        };
+
+        let bucket = StandIn::new(|method, _| {
+            let response = Response::builder();
+            match *method {
+                Method::POST => response.body(
+                    "<InitiateMultipartUploadResult><UploadId>1</UploadId>\
+                     </InitiateMultipartUploadResult>"
+                        .into(),
+                ),
+                _ => response.header("ETag", "\"part\"").body(String::new()),
+            }
+            .unwrap()
+        });
+        let mut writer = S3Writer::new(Arc::new(MultipartUpload::new(bucket.s3(), "a".into())));
+        writer.max_in_flight_parts = 1;
+
+        let (result, _) = writer
+            .write_all(vec![0; 3 * S3_PART_MINIMUM_SIZE + 1])
+            .await;
+        result.unwrap();
+        // the third part waits for the second, which waited for the first
+        let puts = bucket
+            .requests()
+            .into_iter()
+            .filter(|(method, _)| method == Method::PUT)
+            .count();
+        assert_eq!(puts, 2);
+        assert_eq!(writer.uploaded_bytes(), 2 * S3_PART_MINIMUM_SIZE as u64);
+
+        writer.close().await.unwrap();
+        assert_eq!(writer.uploaded_bytes(), 3 * S3_PART_MINIMUM_SIZE as u64 + 1);
+
+        writer.next_part_numer = 1_999;
+        assert_eq!(writer.next_part_size(), 2 * S3_PART_MINIMUM_SIZE);
+        writer.next_part_numer = 9_999;
+        assert_eq!(writer.next_part_size(), 512 * S3_PART_MINIMUM_SIZE);
+        writer.part_size = S3_PART_MAXIMUM_SIZE;
+        assert_eq!(writer.next_part_size(), S3_PART_MAXIMUM_SIZE);
+    }
 }
@@ -1,6 +1,7 @@
+use http::StatusCode;
 use thiserror::Error;
 
-use crate::error::BoxedError;
+use crate::error::{BoxedError, Error as FusioError, ErrorKind};
 
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -23,3 +24,41 @@ pub enum HttpError {
     #[error(transparent)]
     Other(#[from] BoxedError),
 }
+
+impl HttpError {
+    /// Returns the [`ErrorKind`] of this error, derived from the HTTP status if there is one.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            HttpError::HttpNotSuccess { status, .. } => status_kind(*status),
+            #[cfg(any(feature = "tokio-http", feature = "wasm-http"))]
+            HttpError::Reqwest(err) if err.is_timeout() => ErrorKind::Timeout,
+            #[cfg(all(
+                any(feature = "tokio-http", feature = "wasm-http"),
+                not(target_arch = "wasm32")
+            ))]
+            HttpError::Reqwest(err) if err.is_connect() => ErrorKind::Unavailable,
+            #[cfg(any(feature = "tokio-http", feature = "wasm-http"))]
+            HttpError::Reqwest(err) => err.status().map(status_kind).unwrap_or(ErrorKind::Other),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl From<HttpError> for FusioError {
+    fn from(err: HttpError) -> Self {
+        FusioError::with_kind(err.kind(), err)
+    }
+}
+
+pub(crate) fn status_kind(status: StatusCode) -> ErrorKind {
+    match status {
+        StatusCode::NOT_FOUND => ErrorKind::NotFound,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::PermissionDenied,
+        StatusCode::PRECONDITION_FAILED => ErrorKind::PreconditionFailed,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => ErrorKind::Throttled,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => ErrorKind::Timeout,
+        StatusCode::NOT_IMPLEMENTED => ErrorKind::Unsupported,
+        StatusCode::INTERNAL_SERVER_ERROR | StatusCode::BAD_GATEWAY => ErrorKind::Unavailable,
+        _ => ErrorKind::Other,
+    }
+}
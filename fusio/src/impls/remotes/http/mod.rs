@@ -1,12 +1,13 @@
 mod error;
 #[cfg(all(feature = "monoio-http", feature = "completion-based"))]
 pub mod monoio;
+mod retry;
 #[cfg(all(feature = "tokio-http", not(feature = "completion-based")))]
 pub mod tokio;
 #[cfg(all(feature = "wasm-http", not(feature = "completion-based")))]
 pub mod wasm;
 
-use std::{future::Future, pin::Pin};
+use std::{future::Future, pin::Pin, sync::Arc};
 
 use bytes::Bytes;
 pub use error::HttpError;
@@ -15,6 +16,7 @@ use futures_core::Stream;
 use http::{Request, Response};
 use http_body::Body;
 use http_body_util::BodyExt;
+pub use retry::RetryClient;
 
 use crate::error::BoxedError;
 
@@ -84,15 +86,54 @@ impl HttpClient for Box<dyn DynHttpClient> {
         B::Data: Into<Bytes>,
         B::Error: Into<BoxedError>,
     {
-        let (parts, body) = request.into_parts();
-        let request = Request::from_parts(
-            parts,
-            BoxBody::new(
-                body.map_frame(|f| f.map_data(|data| data.into()))
-                    .map_err(|e| HttpError::from(e.into() as BoxedError)),
-            ),
-        );
-        let response = self.as_ref().dyn_send_request(request).await?;
-        Ok(response)
+        self.as_ref().dyn_send_request(box_request(request)).await
+    }
+}
+
+impl HttpClient for Arc<dyn DynHttpClient + '_> {
+    type RespBody = BoxBody;
+
+    async fn send_request<B>(
+        &self,
+        request: Request<B>,
+    ) -> Result<Response<Self::RespBody>, HttpError>
+    where
+        B: Body + Send + MaybeSync + 'static,
+        B::Data: Into<Bytes>,
+        B::Error: Into<BoxedError>,
+    {
+        self.as_ref().dyn_send_request(box_request(request)).await
+    }
+}
+
+fn box_request<B>(request: Request<B>) -> Request<BoxBody>
+where
+    B: Body + Send + MaybeSync + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxedError>,
+{
+    let (parts, body) = request.into_parts();
+    Request::from_parts(
+        parts,
+        BoxBody::new(
+            body.map_frame(|f| f.map_data(|data| data.into()))
+                .map_err(|e| HttpError::from(e.into() as BoxedError)),
+        ),
+    )
+}
+
+/// Creates the client of the HTTP runtime enabled by the features of this crate.
+#[allow(unreachable_code)]
+pub(crate) fn default_client() -> Box<dyn DynHttpClient> {
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "tokio-http", not(feature = "completion-based")))] {
+            Box::new(tokio::TokioClient::new())
+        } else if #[cfg(all(feature = "wasm-http", not(feature = "completion-based")))]{
+            Box::new(wasm::WasmClient::new())
+        } else if #[cfg(all(feature = "monoio-http", feature = "completion-based"))]{
+            Box::new(monoio::MonoioClient::new())
+        } else {
+            unreachable!()
+        }
     }
 }
@@ -0,0 +1,220 @@
+use bytes::Bytes;
+use http::{Method, Request, Response};
+use http_body::Body;
+use http_body_util::{BodyExt, Full};
+
+use super::{error::status_kind, HttpClient, HttpError};
+use crate::{
+    error::{BoxedError, ErrorKind},
+    retry::{sleep, RetryPolicy},
+    MaybeSync,
+};
+
+/// An [`HttpClient`] that resends requests which failed transiently.
+///
+/// A request is retried if sending it failed with a transient error or its response has a status
+/// like `503 Slow Down` or `500 Internal Server Error`. Only idempotent methods are retried
+/// unless [`RetryPolicy::retry_non_idempotent`] is set or the request was throttled, since a
+/// throttled request never took effect. The request body is buffered once, so it can be sent
+/// again.
+///
+/// On monoio, the runtime must be built with `enable_timer` to wait between attempts.
+pub struct RetryClient<C> {
+    inner: C,
+    policy: RetryPolicy,
+}
+
+impl<C: HttpClient> RetryClient<C> {
+    pub fn new(inner: C, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl<C: HttpClient> HttpClient for RetryClient<C> {
+    type RespBody = C::RespBody;
+
+    async fn send_request<B>(
+        &self,
+        request: Request<B>,
+    ) -> Result<Response<Self::RespBody>, HttpError>
+    where
+        B: Body + Send + MaybeSync + 'static,
+        B::Data: Into<Bytes>,
+        B::Error: Into<BoxedError>,
+    {
+        let (parts, body) = request.into_parts();
+        let body = body
+            .map_frame(|frame| frame.map_data(Into::<Bytes>::into))
+            .collect()
+            .await
+            .map_err(|err| HttpError::Other(err.into()))?
+            .to_bytes();
+        let idempotent = is_idempotent(&parts.method);
+
+        let mut attempt = 1;
+        loop {
+            let mut request = Request::new(Full::new(body.clone()));
+            *request.method_mut() = parts.method.clone();
+            *request.uri_mut() = parts.uri.clone();
+            *request.version_mut() = parts.version;
+            *request.headers_mut() = parts.headers.clone();
+            *request.extensions_mut() = parts.extensions.clone();
+
+            let result = self.inner.send_request(request).await;
+            let kind = match &result {
+                Ok(response) => status_kind(response.status()),
+                Err(err) => err.kind(),
+            };
+            let retry = kind.is_transient()
+                && self
+                    .policy
+                    .allows_retry(attempt, idempotent || kind == ErrorKind::Throttled);
+            if !retry {
+                return result;
+            }
+            sleep(self.policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+    use http::{Method, Request, Response, StatusCode};
+    use http_body::Body;
+    use http_body_util::{BodyExt, Empty, Full};
+
+    use super::RetryClient;
+    use crate::{
+        error::BoxedError,
+        remotes::http::{HttpClient, HttpError},
+        retry::RetryPolicy,
+        MaybeSync,
+    };
+
+    /// Responds with the given statuses in order, recording the bodies it received.
+    struct ScriptedClient {
+        statuses: Vec<StatusCode>,
+        calls: AtomicUsize,
+        bodies: Mutex<Vec<Bytes>>,
+    }
+
+    impl ScriptedClient {
+        fn new(statuses: Vec<StatusCode>) -> Self {
+            Self {
+                statuses,
+                calls: AtomicUsize::new(0),
+                bodies: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl HttpClient for &ScriptedClient {
+        type RespBody = Empty<Bytes>;
+
+        async fn send_request<B>(
+            &self,
+            request: Request<B>,
+        ) -> Result<Response<Self::RespBody>, HttpError>
+        where
+            B: Body + Send + MaybeSync + 'static,
+            B::Data: Into<Bytes>,
+            B::Error: Into<BoxedError>,
+        {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let body = request
+                .into_body()
+                .map_frame(|frame| frame.map_data(Into::<Bytes>::into))
+                .collect()
+                .await
+                .map_err(|err| HttpError::Other(err.into()))?;
+            self.bodies.lock().unwrap().push(body.to_bytes());
+
+            Ok(Response::builder()
+                .status(self.statuses[call.min(self.statuses.len() - 1)])
+                .body(Empty::new())?)
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .max_attempts(3)
+            .base_backoff(Duration::from_millis(1))
+    }
+
+    fn request(method: Method) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(method)
+            .uri("http://localhost/bucket/key")
+            .body(Full::new(Bytes::from_static(b"part")))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_transient_status() {
+        let inner = ScriptedClient::new(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::OK,
+        ]);
+        let client = RetryClient::new(&inner, policy());
+        let response = client.send_request(request(Method::PUT)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+        // the body is resent on every attempt
+        assert!(inner
+            .bodies
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|body| body.as_ref() == b"part"));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let inner = ScriptedClient::new(vec![StatusCode::SERVICE_UNAVAILABLE]);
+        let client = RetryClient::new(&inner, policy());
+        let response = client.send_request(request(Method::GET)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_non_idempotent_requests() {
+        let inner = ScriptedClient::new(vec![StatusCode::INTERNAL_SERVER_ERROR, StatusCode::OK]);
+        let client = RetryClient::new(&inner, policy());
+        let response = client.send_request(request(Method::POST)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        // throttled requests never took effect and are retried regardless of the method
+        let inner = ScriptedClient::new(vec![StatusCode::SERVICE_UNAVAILABLE, StatusCode::OK]);
+        let client = RetryClient::new(&inner, policy());
+        let response = client.send_request(request(Method::POST)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let inner = ScriptedClient::new(vec![StatusCode::NOT_FOUND, StatusCode::OK]);
+        let client = RetryClient::new(&inner, policy());
+        let response = client.send_request(request(Method::GET)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,352 @@
+use futures_core::Stream;
+
+use super::{sleep, RetryPolicy};
+#[cfg(feature = "bytes")]
+use crate::fs::{GetResult, PutResult};
+use crate::{
+    error::Error,
+    fs::{FileMeta, FileSystemTag, Fs, ListOptions, OpenOptions},
+    path::Path,
+    IoBuf, IoBufMut, MaybeSend, Read, Write,
+};
+
+/// A file system that retries the operations of `F` that failed transiently.
+///
+/// Operations that cannot be repeated safely, i.e. creating a new file, conditional writes,
+/// [`Fs::link`] and [`Fs::rename`], are only retried if [`RetryPolicy::retry_non_idempotent`] is
+/// set. Files opened through it retry their reads; writes are never retried, because a failed
+/// write may already have been partially applied. Neither is [`Fs::put_stream`], whose stream can
+/// not be replayed.
+///
+/// Like every use of a [`RetryPolicy`], retrying on monoio requires a runtime built with
+/// `enable_timer`.
+#[derive(Debug, Clone)]
+pub struct RetryFs<F> {
+    inner: F,
+    policy: RetryPolicy,
+}
+
+impl<F: Fs> RetryFs<F> {
+    pub fn new(inner: F, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: Fs> Fs for RetryFs<F> {
+    type File = RetryFile<F::File>;
+
+    fn file_system(&self) -> FileSystemTag {
+        self.inner.file_system()
+    }
+
+    async fn open_options(&self, path: &Path, options: OpenOptions) -> Result<Self::File, Error> {
+        let file = self
+            .policy
+            .retry(!options.create_new, || {
+                self.inner.open_options(path, options.clone())
+            })
+            .await?;
+
+        Ok(RetryFile::new(file, self.policy.clone()))
+    }
+
+    async fn create_dir_all(path: &Path) -> Result<(), Error> {
+        F::create_dir_all(path).await
+    }
+
+    /// Retries listing until the first page is returned. Errors of later pages are passed on.
+    async fn list(
+        &self,
+        path: &Path,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        self.policy.retry(true, || self.inner.list(path)).await
+    }
+
+    /// Retries listing until the first page is returned, like [`Fs::list`].
+    async fn list_with(
+        &self,
+        path: &Path,
+        options: ListOptions,
+    ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+        self.policy
+            .retry(true, || self.inner.list_with(path, options.clone()))
+            .await
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+        self.policy.retry(true, || self.inner.metadata(path)).await
+    }
+
+    async fn remove(&self, path: &Path) -> Result<(), Error> {
+        self.policy.retry(true, || self.inner.remove(path)).await
+    }
+
+    /// Retries the batch as a whole if it fails, but not the files that could not be removed.
+    async fn remove_batch(&self, paths: &[Path]) -> Result<Vec<Result<(), Error>>, Error> {
+        self.policy
+            .retry(true, || self.inner.remove_batch(paths))
+            .await
+    }
+
+    async fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        self.policy
+            .retry(true, || self.inner.remove_dir_all(path))
+            .await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.policy.retry(true, || self.inner.copy(from, to)).await
+    }
+
+    async fn link(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.policy.retry(false, || self.inner.link(from, to)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.policy
+            .retry(false, || self.inner.rename(from, to))
+            .await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put_stream<S>(
+        &self,
+        path: &Path,
+        stream: S,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error>
+    where
+        S: Stream<Item = Result<bytes::Bytes, Error>> + MaybeSend,
+    {
+        self.inner.put_stream(path, stream, options).await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn put(
+        &self,
+        path: &Path,
+        buf: bytes::Bytes,
+        options: OpenOptions,
+    ) -> Result<PutResult, Error> {
+        let idempotent = !options.create_new && options.if_match.is_none();
+        self.policy
+            .retry(idempotent, || {
+                self.inner.put(path, buf.clone(), options.clone())
+            })
+            .await
+    }
+
+    #[cfg(feature = "bytes")]
+    async fn get(&self, path: &Path) -> Result<GetResult, Error> {
+        self.policy.retry(true, || self.inner.get(path)).await
+    }
+
+    fn atomic_rename(&self) -> bool {
+        self.inner.atomic_rename()
+    }
+}
+
+/// A file opened by [`RetryFs`], which retries reads that failed transiently.
+pub struct RetryFile<F> {
+    inner: F,
+    policy: RetryPolicy,
+}
+
+impl<F> RetryFile<F> {
+    pub fn new(inner: F, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: Read> Read for RetryFile<F> {
+    async fn read_exact_at<B: IoBufMut>(&mut self, mut buf: B, pos: u64) -> (Result<(), Error>, B) {
+        let mut attempt = 1;
+        loop {
+            let result;
+            (result, buf) = self.inner.read_exact_at(buf, pos).await;
+            match result {
+                Err(err) if self.policy.should_retry(&err, attempt, true) => {
+                    sleep(self.policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return (result, buf),
+            }
+        }
+    }
+
+    async fn read_to_end_at(&mut self, mut buf: Vec<u8>, pos: u64) -> (Result<(), Error>, Vec<u8>) {
+        let len = buf.len();
+        let mut attempt = 1;
+        loop {
+            let result;
+            (result, buf) = self.inner.read_to_end_at(buf, pos).await;
+            match result {
+                Err(err) if self.policy.should_retry(&err, attempt, true) => {
+                    // drop whatever the failed attempt appended
+                    buf.truncate(len);
+                    sleep(self.policy.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return (result, buf),
+            }
+        }
+    }
+
+    async fn size(&self) -> Result<u64, Error> {
+        self.policy.retry(true, || self.inner.size()).await
+    }
+}
+
+impl<F: Write> Write for RetryFile<F> {
+    async fn write_all<B: IoBuf>(&mut self, buf: B) -> (Result<(), Error>, B) {
+        self.inner.write_all(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush().await
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        self.inner.close().await
+    }
+}
+
+#[cfg(all(test, feature = "tokio", not(feature = "completion-based")))]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures_core::Stream;
+
+    use super::RetryFs;
+    use crate::{
+        error::{Error, ErrorKind},
+        fs::{FileMeta, FileSystemTag, Fs, OpenOptions},
+        impls::memory::{MemoryFile, MemoryFs},
+        path::Path,
+        retry::RetryPolicy,
+        MaybeSend,
+    };
+
+    /// Fails the first `failures` calls of every operation with a throttling error.
+    struct FlakyFs {
+        inner: MemoryFs,
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl FlakyFs {
+        fn check(&self) -> Result<(), Error> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(Error::with_kind(ErrorKind::Throttled, "slow down"));
+            }
+            Ok(())
+        }
+    }
+
+    impl Fs for FlakyFs {
+        type File = MemoryFile;
+
+        fn file_system(&self) -> FileSystemTag {
+            FileSystemTag::Memory
+        }
+
+        async fn open_options(
+            &self,
+            path: &Path,
+            options: OpenOptions,
+        ) -> Result<Self::File, Error> {
+            self.check()?;
+            self.inner.open_options(path, options).await
+        }
+
+        async fn create_dir_all(_: &Path) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn list(
+            &self,
+            path: &Path,
+        ) -> Result<impl Stream<Item = Result<FileMeta, Error>> + MaybeSend, Error> {
+            self.check()?;
+            self.inner.list(path).await
+        }
+
+        async fn metadata(&self, path: &Path) -> Result<FileMeta, Error> {
+            self.check()?;
+            self.inner.metadata(path).await
+        }
+
+        async fn remove(&self, path: &Path) -> Result<(), Error> {
+            self.check()?;
+            self.inner.remove(path).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+            self.check()?;
+            self.inner.copy(from, to).await
+        }
+
+        async fn link(&self, from: &Path, to: &Path) -> Result<(), Error> {
+            self.check()?;
+            self.inner.link(from, to).await
+        }
+
+        async fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+            self.check()?;
+            self.inner.rename(from, to).await
+        }
+    }
+
+    fn flaky_fs(failures: usize) -> (RetryFs<FlakyFs>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let fs = FlakyFs {
+            inner: MemoryFs::new(),
+            failures,
+            calls: calls.clone(),
+        };
+        let policy = RetryPolicy::default()
+            .max_attempts(3)
+            .base_backoff(Duration::from_millis(1));
+
+        (RetryFs::new(fs, policy), calls)
+    }
+
+    #[tokio::test]
+    async fn test_retry_fs() {
+        let path = Path::parse("retry.file").unwrap();
+
+        let (fs, calls) = flaky_fs(2);
+        fs.open_options(&path, OpenOptions::default().create(true))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let (fs, calls) = flaky_fs(3);
+        let err = fs.metadata(&path).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Throttled);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // renames are not idempotent and fail on the first error
+        let (fs, calls) = flaky_fs(1);
+        assert!(fs.rename(&path, &path.child("renamed")).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,340 @@
+//! Retrying of transient failures.
+//!
+//! A [`RetryPolicy`] decides whether a failed operation is attempted again and how long to wait
+//! before doing so. It is applied by [`RetryFs`] to any file system and by
+//! [`RetryClient`](crate::remotes::http::RetryClient) to the requests of an HTTP client.
+
+#[cfg(feature = "fs")]
+mod fs;
+
+use std::{collections::hash_map::RandomState, future::Future, hash::BuildHasher, time::Duration};
+
+#[cfg(feature = "fs")]
+pub use fs::{RetryFile, RetryFs};
+
+use crate::error::Error;
+
+/// Configures how often and how late failed operations are retried.
+///
+/// Only errors whose [`ErrorKind`](crate::error::ErrorKind) is transient are retried. The delay
+/// before the `n`-th retry grows exponentially from [`RetryPolicy::base_backoff`], is capped by
+/// [`RetryPolicy::max_backoff`] and, with jitter enabled, is drawn uniformly from zero up to that
+/// value so that concurrent clients do not retry in lockstep.
+///
+/// The delays are waited out on the timer of the runtime, see [`sleep`]. On monoio, that timer
+/// is off by default, so runtimes retrying with a policy must be built with `enable_timer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: bool,
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Sets the number of attempts including the first one. Values below one are treated as one.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn base_backoff(mut self, base_backoff: Duration) -> Self {
+        self.base_backoff = base_backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Whether operations that may have taken effect before failing, e.g. an HTTP `POST` or a
+    /// rename, are retried as well. Retrying them can apply the operation twice or report an
+    /// error for an operation that succeeded.
+    pub fn retry_non_idempotent(mut self, retry_non_idempotent: bool) -> Self {
+        self.retry_non_idempotent = retry_non_idempotent;
+        self
+    }
+
+    /// Returns `true` if an operation that failed with `err` on its `attempt`-th try, counting
+    /// from one, should be attempted again.
+    pub fn should_retry(&self, err: &Error, attempt: usize, idempotent: bool) -> bool {
+        self.allows_retry(attempt, idempotent) && err.kind().is_transient()
+    }
+
+    pub(crate) fn allows_retry(&self, attempt: usize, idempotent: bool) -> bool {
+        attempt < self.max_attempts && (idempotent || self.retry_non_idempotent)
+    }
+
+    /// Returns the delay before retrying an operation that failed on its `attempt`-th try.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let backoff = self
+            .base_backoff
+            .checked_mul(2u32.saturating_pow(exponent))
+            .unwrap_or(Duration::MAX)
+            .min(self.max_backoff);
+        if !self.jitter {
+            return backoff;
+        }
+        // `RandomState` is seeded differently for every instance, which is random enough to
+        // spread retries out without pulling in a random number generator.
+        let random = RandomState::new().hash_one(attempt);
+
+        backoff.mul_f64(random as f64 / u64::MAX as f64)
+    }
+
+    /// Runs `op` until it succeeds, fails with an error that is not retried or runs out of
+    /// attempts, sleeping for the backoff between attempts.
+    pub(crate) async fn retry<T, Fut>(
+        &self,
+        idempotent: bool,
+        mut op: impl FnMut() -> Fut,
+    ) -> Result<T, Error>
+    where
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(err) if self.should_retry(&err, attempt, idempotent) => {
+                    sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Waits for `duration` without blocking the runtime.
+///
+/// The timer of the runtime is used, i.e. that of the browser, monoio, or tokio, which
+/// tokio-uring and the reqwest client run on. Builds without any of these runtimes wake the task
+/// up from a helper thread instead.
+///
+/// # Panics
+///
+/// On monoio, if the current runtime was built without `enable_timer`, which is monoio's
+/// default.
+pub async fn sleep(duration: Duration) {
+    if duration.is_zero() {
+        return;
+    }
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_arch = "wasm32", any(feature = "opfs", feature = "wasm-http")))] {
+            wasm::sleep(duration).await
+        } else if #[cfg(feature = "monoio")] {
+            monoio::time::sleep(duration).await
+        } else if #[cfg(any(
+            all(feature = "tokio", not(feature = "completion-based")),
+            all(feature = "tokio-uring", target_os = "linux"),
+            feature = "tokio-http"
+        ))] {
+            tokio::time::sleep(duration).await
+        } else {
+            thread::Sleep::new(duration).await
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", any(feature = "opfs", feature = "wasm-http")))]
+mod wasm {
+    use std::time::Duration;
+
+    use js_sys::{
+        wasm_bindgen::{JsCast, JsValue},
+        Function, Promise, Reflect,
+    };
+    use wasm_bindgen_futures::JsFuture;
+
+    pub(super) async fn sleep(duration: Duration) {
+        // `setTimeout` is looked up on the global object, so this works in windows and workers
+        let promise = Promise::new(&mut |resolve, _| {
+            let set_timeout = Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))
+                .expect("setTimeout is not available")
+                .unchecked_into::<Function>();
+            set_timeout
+                .call2(
+                    &JsValue::NULL,
+                    &resolve,
+                    &JsValue::from_f64(duration.as_millis() as f64),
+                )
+                .expect("setTimeout failed");
+        });
+        let _ = JsFuture::from(promise).await;
+    }
+}
+
+#[cfg(not(any(
+    all(target_arch = "wasm32", any(feature = "opfs", feature = "wasm-http")),
+    feature = "monoio",
+    all(feature = "tokio", not(feature = "completion-based")),
+    all(feature = "tokio-uring", target_os = "linux"),
+    feature = "tokio-http"
+)))]
+mod thread {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Waker},
+        time::Duration,
+    };
+
+    #[derive(Default)]
+    struct State {
+        done: bool,
+        waker: Option<Waker>,
+    }
+
+    pub(super) struct Sleep {
+        duration: Duration,
+        state: Option<Arc<Mutex<State>>>,
+    }
+
+    impl Sleep {
+        pub(super) fn new(duration: Duration) -> Self {
+            Self {
+                duration,
+                state: None,
+            }
+        }
+    }
+
+    impl Future for Sleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let duration = self.duration;
+            let state = self.state.get_or_insert_with(|| {
+                let state = Arc::new(Mutex::new(State::default()));
+                let timer = state.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(duration);
+                    let mut state = timer.lock().unwrap();
+                    state.done = true;
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                });
+                state
+            });
+            let mut state = state.lock().unwrap();
+            if state.done {
+                return Poll::Ready(());
+            }
+            state.waker = Some(cx.waker().clone());
+
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use super::RetryPolicy;
+    use crate::error::{Error, ErrorKind};
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy::default()
+            .base_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .jitter(false);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(usize::MAX), Duration::from_secs(1));
+
+        let policy = policy.jitter(true);
+        for attempt in 1..10 {
+            assert!(policy.backoff(attempt) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn retries_transient_errors_only() {
+        let policy = RetryPolicy::default().max_attempts(3);
+        let throttled = Error::with_kind(ErrorKind::Throttled, "slow down");
+        let reset = Error::Io(io::ErrorKind::ConnectionReset.into());
+        let not_found = Error::Io(io::ErrorKind::NotFound.into());
+
+        assert!(policy.should_retry(&throttled, 1, true));
+        assert!(policy.should_retry(&reset, 2, true));
+        assert!(!policy.should_retry(&reset, 3, true));
+        assert!(!policy.should_retry(&not_found, 1, true));
+        assert!(!policy.should_retry(&throttled, 1, false));
+        assert!(policy
+            .retry_non_idempotent(true)
+            .should_retry(&throttled, 1, false));
+        assert!(!RetryPolicy::none().should_retry(&throttled, 1, true));
+    }
+
+    #[cfg(all(feature = "tokio", not(feature = "completion-based")))]
+    #[tokio::test]
+    async fn retry_until_success() {
+        let policy = RetryPolicy::default().base_backoff(Duration::from_millis(1));
+
+        let mut attempts = 0;
+        let result = policy
+            .retry(true, || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 3 {
+                        Err(Error::with_kind(ErrorKind::Unavailable, "internal error"))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        attempts = 0;
+        let result = policy
+            .retry(true, || {
+                attempts += 1;
+                async { Err::<(), _>(Error::with_kind(ErrorKind::Timeout, "timed out")) }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Timeout);
+        assert_eq!(attempts, 3);
+    }
+
+    #[cfg(all(feature = "monoio", not(target_arch = "wasm32")))]
+    #[monoio::test(enable_timer = true)]
+    async fn sleep_on_monoio_timer() {
+        let start = std::time::Instant::now();
+        super::sleep(Duration::from_millis(10)).await;
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+}
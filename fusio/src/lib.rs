@@ -188,7 +188,7 @@ mod tests {
         use std::collections::HashSet;
 
         use fusio_core::error::Error;
-        use futures_util::StreamExt;
+        use futures_util::{StreamExt, TryStreamExt};
         use tempfile::TempDir;
 
         use crate::{fs::OpenOptions, path::Path, DynFs};
@@ -254,6 +254,28 @@ mod tests {
             let (result, buf) = file.read_exact_at(vec![0u8; 12], 12).await;
             result.unwrap();
             assert_eq!(buf.as_slice(), b"Hello! world");
+            let chunks = file.read_stream(7..24).try_collect::<Vec<_>>().await?;
+            assert_eq!(chunks.concat(), b"fusioHello! world");
+        }
+        {
+            let meta = fs
+                .metadata(
+                    &Path::from_absolute_path(&work_file_path)
+                        .map_err(|err| Error::Path(Box::new(err)))?,
+                )
+                .await?;
+            assert_eq!(meta.size, 24);
+            assert!(!meta.is_dir);
+            assert!(meta.last_modified.is_some());
+
+            let meta = fs
+                .metadata(
+                    &Path::from_absolute_path(&work_dir_path)
+                        .map_err(|err| Error::Path(Box::new(err)))?,
+                )
+                .await?;
+            assert!(meta.is_dir);
+            assert_eq!(meta.size, 0);
         }
 
         Ok(())
@@ -443,6 +465,307 @@ mod tests {
         write_and_read(write_file, read_file).await;
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(unused)]
+    async fn test_local_fs_rename<F: crate::fs::Fs>(fs: F) -> Result<(), Error> {
+        use tempfile::TempDir;
+
+        use crate::{fs::OpenOptions, path::Path};
+
+        let tmp_dir = TempDir::new()?;
+        let from = Path::from_absolute_path(tmp_dir.path().join("from.file"))
+            .map_err(|err| Error::Path(Box::new(err)))?;
+        let to = Path::from_absolute_path(tmp_dir.path().join("to.file"))
+            .map_err(|err| Error::Path(Box::new(err)))?;
+
+        for (path, content) in [(&from, "Hello! fusio"), (&to, "stale")] {
+            let mut file = fs
+                .open_options(path, OpenOptions::default().create(true).write(true))
+                .await?;
+            file.write_all(content.as_bytes()).await.0?;
+            file.close().await?;
+        }
+
+        assert!(fs.atomic_rename());
+        fs.rename(&from, &to).await?;
+
+        assert_eq!(
+            fs.metadata(&from).await.unwrap_err().kind(),
+            crate::error::ErrorKind::NotFound
+        );
+        let (result, buf) = fs.open(&to).await?.read_to_end_at(vec![], 0).await;
+        result?;
+        assert_eq!(buf.as_slice(), b"Hello! fusio");
+        assert!(fs.rename(&from, &to).await.is_err());
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(unused)]
+    async fn test_local_fs_conditional_write<F: crate::fs::Fs>(fs: F) -> Result<(), Error> {
+        use tempfile::TempDir;
+
+        use crate::{fs::OpenOptions, path::Path};
+
+        let tmp_dir = TempDir::new()?;
+        let path = Path::from_absolute_path(tmp_dir.path().join("manifest"))
+            .map_err(|err| Error::Path(Box::new(err)))?;
+
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create_new(true))
+            .await?;
+        file.write_all("v1".as_bytes()).await.0?;
+        file.close().await?;
+        assert!(matches!(
+            fs.open_options(&path, OpenOptions::default().create_new(true))
+                .await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+
+        let e_tag = fs.metadata(&path).await?.e_tag.unwrap();
+        let mut file = fs
+            .open_options(
+                &path,
+                OpenOptions::default()
+                    .truncate(true)
+                    .if_match(e_tag.clone()),
+            )
+            .await?;
+        file.write_all("v2".as_bytes()).await.0?;
+        file.close().await?;
+        drop(file);
+        // "v2" keeps the length of "v1", and possibly the modification time, yet not the ETag
+        assert_ne!(fs.metadata(&path).await?.e_tag, Some(e_tag.clone()));
+        assert!(matches!(
+            fs.open_options(&path, OpenOptions::default().truncate(true).if_match(e_tag))
+                .await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+
+        let (result, buf) = fs.open(&path).await?.read_to_end_at(vec![], 0).await;
+        result?;
+        assert_eq!(buf.as_slice(), b"v2");
+
+        Ok(())
+    }
+
+    /// Opens a file with an ETag to match while another writer holds it, which fails rather than
+    /// waiting on runtimes that would block their only thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(unused)]
+    async fn test_local_fs_held_if_match<F: crate::fs::Fs>(fs: F) -> Result<(), Error> {
+        use tempfile::TempDir;
+
+        use crate::{fs::OpenOptions, path::Path};
+
+        let tmp_dir = TempDir::new()?;
+        let path = Path::from_absolute_path(tmp_dir.path().join("manifest"))
+            .map_err(|err| Error::Path(Box::new(err)))?;
+        let mut file = fs
+            .open_options(&path, OpenOptions::default().create_new(true))
+            .await?;
+        file.write_all("v1".as_bytes()).await.0?;
+        file.close().await?;
+
+        let e_tag = fs.metadata(&path).await?.e_tag.unwrap();
+        let options = OpenOptions::default().if_match(e_tag);
+        let mut held = fs.open_options(&path, options.clone()).await?;
+        assert!(matches!(
+            fs.open_options(&path, options.clone()).await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+        held.close().await?;
+        // the writer that held the file changed its ETag, even without writing
+        assert!(matches!(
+            fs.open_options(&path, options).await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+        let e_tag = fs.metadata(&path).await?.e_tag.unwrap();
+        fs.open_options(&path, OpenOptions::default().if_match(e_tag))
+            .await?
+            .close()
+            .await?;
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(unused)]
+    async fn test_local_fs_list_with<F: crate::fs::Fs>(fs: F) -> Result<(), Error> {
+        use futures_util::TryStreamExt;
+        use tempfile::TempDir;
+
+        use crate::{
+            fs::{ListOptions, OpenOptions},
+            path::Path,
+        };
+
+        let tmp_dir = TempDir::new()?;
+        let dir =
+            Path::from_absolute_path(tmp_dir.path()).map_err(|err| Error::Path(Box::new(err)))?;
+        for name in ["a.txt", "b/c.txt", "b/d/e.txt", "b-x"] {
+            let mut file = fs
+                .open_options(
+                    &Path::from_absolute_path(tmp_dir.path().join(name))
+                        .map_err(|err| Error::Path(Box::new(err)))?,
+                    OpenOptions::default().create(true).write(true),
+                )
+                .await?;
+            file.write_all(name.as_bytes()).await.0?;
+            file.close().await?;
+        }
+        let list = |options: ListOptions| {
+            let fs = &fs;
+            let dir = &dir;
+            async move {
+                let entries = fs
+                    .list_with(dir, options)
+                    .await?
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                Ok::<_, Error>(
+                    entries
+                        .into_iter()
+                        .map(|meta| {
+                            let name = meta.path.as_ref()[dir.as_ref().len() + 1..].to_string();
+                            (name, meta.is_dir)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            }
+        };
+        let entry = |name: &str, is_dir: bool| (name.to_string(), is_dir);
+
+        // `-` sorts before the `/` that follows the name of a directory
+        assert_eq!(
+            list(ListOptions::default()).await?,
+            [entry("a.txt", false), entry("b-x", false), entry("b", true)]
+        );
+        assert_eq!(
+            list(ListOptions::default().recursive(true)).await?,
+            [
+                entry("a.txt", false),
+                entry("b-x", false),
+                entry("b/c.txt", false),
+                entry("b/d/e.txt", false),
+            ]
+        );
+        assert_eq!(
+            list(
+                ListOptions::default()
+                    .recursive(true)
+                    .start_after(dir.child("b").child("c.txt"))
+                    .limit(2)
+            )
+            .await?,
+            [entry("b/d/e.txt", false)]
+        );
+        assert_eq!(
+            list(ListOptions::default().limit(2)).await?,
+            [entry("a.txt", false), entry("b-x", false)]
+        );
+        assert!(matches!(
+            list(ListOptions::default().delimiter("-")).await,
+            Err(Error::Unsupported { .. })
+        ));
+        // like a prefix without objects, a missing directory has no entries
+        assert!(fs
+            .list_with(&dir.child("missing"), ListOptions::default())
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(unused)]
+    async fn test_local_fs_remove_batch<F: crate::fs::Fs>(fs: F) -> Result<(), Error> {
+        use tempfile::TempDir;
+
+        use crate::{error::ErrorKind, fs::OpenOptions, path::Path};
+
+        let tmp_dir = TempDir::new()?;
+        let dir = Path::from_absolute_path(tmp_dir.path().join("table"))
+            .map_err(|err| Error::Path(Box::new(err)))?;
+        let paths = ["a", "b", "c/d"]
+            .map(|name| Path::from_absolute_path(tmp_dir.path().join("table").join(name)).unwrap());
+        for path in &paths {
+            let mut file = fs
+                .open_options(path, OpenOptions::default().create(true).write(true))
+                .await?;
+            file.write_all(&b"data"[..]).await.0?;
+            file.close().await?;
+        }
+
+        let results = fs
+            .remove_batch(&[paths[0].clone(), dir.child("missing")])
+            .await?;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(
+            fs.metadata(&paths[0]).await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        fs.remove_dir_all(&dir).await?;
+        assert!(!tmp_dir.path().join("table").exists());
+        // a directory that is already gone counts as removed
+        fs.remove_dir_all(&dir).await?;
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "bytes", not(target_arch = "wasm32")))]
+    #[allow(unused)]
+    async fn test_local_fs_put_get<F: crate::fs::Fs>(fs: F) -> Result<(), Error> {
+        use bytes::Bytes;
+        use tempfile::TempDir;
+
+        use crate::{fs::OpenOptions, path::Path};
+
+        let tmp_dir = TempDir::new()?;
+        let path = Path::from_absolute_path(tmp_dir.path().join("schema"))
+            .map_err(|err| Error::Path(Box::new(err)))?;
+
+        let put = fs
+            .put(&path, Bytes::from_static(b"v1"), OpenOptions::default())
+            .await?;
+        assert_eq!(put.size, 2);
+        let put = fs
+            .put(&path, Bytes::from_static(b"v2!"), OpenOptions::default())
+            .await?;
+        let get = fs.get(&path).await?;
+        assert_eq!(get.bytes.as_ref(), b"v2!");
+        assert_eq!(get.meta.size, 3);
+        assert_eq!(get.meta.e_tag, put.e_tag);
+        // the content is staged next to the file and renamed into place
+        assert_eq!(std::fs::read_dir(tmp_dir.path())?.count(), 1);
+
+        assert!(matches!(
+            fs.put(
+                &path,
+                Bytes::from_static(b"v3"),
+                OpenOptions::default().create_new(true)
+            )
+            .await,
+            Err(Error::PreconditionFailed { .. })
+        ));
+        let e_tag = put.e_tag.unwrap();
+        fs.put(
+            &path,
+            Bytes::from_static(b"v3"),
+            OpenOptions::default().if_match(e_tag),
+        )
+        .await?;
+        assert_eq!(fs.get(&path).await?.bytes.as_ref(), b"v3");
+
+        Ok(())
+    }
+
     #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
     #[tokio::test(flavor = "multi_thread")]
     async fn test_tokio_fs() {
@@ -450,6 +773,12 @@ mod tests {
 
         test_local_fs_read_write(TokioFs).await.unwrap();
         test_local_fs_copy_link(TokioFs).await.unwrap();
+        test_local_fs_rename(TokioFs).await.unwrap();
+        test_local_fs_conditional_write(TokioFs).await.unwrap();
+        test_local_fs_list_with(TokioFs).await.unwrap();
+        test_local_fs_remove_batch(TokioFs).await.unwrap();
+        #[cfg(feature = "bytes")]
+        test_local_fs_put_get(TokioFs).await.unwrap();
     }
 
     #[cfg(all(feature = "tokio-uring", target_os = "linux"))]
@@ -460,6 +789,13 @@ mod tests {
         tokio_uring::start(async {
             test_local_fs_read_write(TokioUringFs).await.unwrap();
             test_local_fs_copy_link(TokioUringFs).await.unwrap();
+            test_local_fs_rename(TokioUringFs).await.unwrap();
+            test_local_fs_conditional_write(TokioUringFs).await.unwrap();
+            test_local_fs_held_if_match(TokioUringFs).await.unwrap();
+            test_local_fs_list_with(TokioUringFs).await.unwrap();
+            test_local_fs_remove_batch(TokioUringFs).await.unwrap();
+            #[cfg(feature = "bytes")]
+            test_local_fs_put_get(TokioUringFs).await.unwrap();
         })
     }
 
@@ -470,6 +806,13 @@ mod tests {
 
         test_local_fs_read_write(MonoIoFs).await.unwrap();
         test_local_fs_copy_link(MonoIoFs).await.unwrap();
+        test_local_fs_rename(MonoIoFs).await.unwrap();
+        test_local_fs_conditional_write(MonoIoFs).await.unwrap();
+        test_local_fs_held_if_match(MonoIoFs).await.unwrap();
+        test_local_fs_list_with(MonoIoFs).await.unwrap();
+        test_local_fs_remove_batch(MonoIoFs).await.unwrap();
+        #[cfg(feature = "bytes")]
+        test_local_fs_put_get(MonoIoFs).await.unwrap();
     }
 
     #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
@@ -4,7 +4,13 @@ pub(crate) mod tests {
 
     wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
-    use fusio::{disk::OPFS, fs::OpenOptions, path::Path, DynFs, Read, Write};
+    use fusio::{
+        disk::OPFS,
+        error::ErrorKind,
+        fs::{ListOptions, OpenOptions},
+        path::Path,
+        DynFs, Read, Write,
+    };
     use futures_util::StreamExt;
     use wasm_bindgen_test::wasm_bindgen_test;
 
@@ -91,6 +97,91 @@ pub(crate) mod tests {
         remove_all(&fs, &["test_opfs_dir"]).await;
     }
 
+    #[wasm_bindgen_test]
+    async fn test_opfs_list_with() {
+        let fs = OPFS;
+        for name in ["a.txt", "b/c.txt", "b/d/e.txt", "b-x"] {
+            let path = Path::from_opfs_path(format!("test_opfs_list_with/{name}")).unwrap();
+            if let Some(parent) = name.rsplit_once('/').map(|(parent, _)| parent) {
+                fs.create_dir_all(
+                    &Path::from_opfs_path(format!("test_opfs_list_with/{parent}")).unwrap(),
+                )
+                .await
+                .unwrap();
+            }
+            let mut file = fs
+                .open_options(&path, OpenOptions::default().create(true))
+                .await
+                .unwrap();
+            file.close().await.unwrap();
+        }
+        let base_path = Path::from_opfs_path("test_opfs_list_with").unwrap();
+        let list = |options: ListOptions| {
+            let fs = &fs;
+            let base_path = &base_path;
+            async move {
+                fs.list_with(base_path, options)
+                    .await
+                    .unwrap()
+                    .map(|meta| {
+                        let meta = meta.unwrap();
+                        (meta.path.to_string(), meta.is_dir)
+                    })
+                    .collect::<Vec<_>>()
+                    .await
+            }
+        };
+        let entry = |name: &str, is_dir: bool| (format!("test_opfs_list_with/{name}"), is_dir);
+
+        // `-` sorts before the `/` that follows the name of a directory
+        assert_eq!(
+            list(ListOptions::default()).await,
+            [entry("a.txt", false), entry("b-x", false), entry("b", true)]
+        );
+        assert_eq!(
+            list(
+                ListOptions::default()
+                    .recursive(true)
+                    .start_after(base_path.child("a.txt"))
+                    .limit(2)
+            )
+            .await,
+            [entry("b-x", false), entry("b/c.txt", false)]
+        );
+        assert!(fs
+            .list_with(&"test_opfs_missing".into(), ListOptions::default())
+            .await
+            .unwrap()
+            .next()
+            .await
+            .is_none());
+
+        remove_all(&fs, &["test_opfs_list_with"]).await;
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_opfs_remove_dir_all() {
+        let fs = OPFS;
+        let dir = Path::from_opfs_path("test_opfs_remove_dir_all").unwrap();
+        fs.create_dir_all(&dir.child("sub_dir")).await.unwrap();
+        let mut file = fs
+            .open_options(
+                &dir.child("sub_dir").child("file"),
+                OpenOptions::default().create(true),
+            )
+            .await
+            .unwrap();
+        file.close().await.unwrap();
+
+        fs.remove_dir_all(&dir).await.unwrap();
+        assert_eq!(
+            fs.metadata(&dir).await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        // a directory that is already gone counts as removed
+        fs.remove_dir_all(&dir).await.unwrap();
+    }
+
     #[wasm_bindgen_test]
     async fn test_opfs_read_write() {
         let fs = OPFS;